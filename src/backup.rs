@@ -0,0 +1,300 @@
+use std::{
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use salvo::{handler, http::StatusCode, writing::Json, Depot, Request, Response};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::{macros::format_description, OffsetDateTime, UtcOffset};
+use tokio::{sync::Mutex, task::JoinHandle};
+
+use crate::config::{AdminSettings, BackupSettings};
+use crate::error::{MindPulseError, MindPulseResult};
+use crate::statistics::{
+    obtain_statistics, ExclusiveLock, SharedStatisticsStore, SqliteStore, SCHEMA_VERSION,
+};
+
+/// 备份文件名的前缀和后缀，轮换时只处理符合该格式的文件
+const BACKUP_PREFIX: &str = "mind_pulse-";
+const BACKUP_SUFFIX: &str = ".sqlite";
+
+/// 一次备份的结果
+#[derive(Debug, Serialize)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    /// 文件大小（字节）
+    pub size: u64,
+}
+
+/// 备份目录及轮换策略，通过 `Depot` 注入到管理接口
+pub struct Backups {
+    dir: PathBuf,
+    keep: usize,
+    utc_offset: UtcOffset,
+    /// 管理接口令牌的摘要，比较摘要避免按字节提前返回泄露令牌内容
+    token_digest: Option<[u8; 32]>,
+    /// 同一时间只进行一次备份
+    lock: Mutex<()>,
+}
+
+impl Backups {
+    pub fn new(settings: &BackupSettings, admin: &AdminSettings, utc_offset: UtcOffset) -> Self {
+        Self {
+            dir: settings.dir.clone(),
+            keep: settings.keep,
+            utc_offset,
+            token_digest: admin
+                .token
+                .as_deref()
+                .map(|token| Sha256::digest(token.trim().as_bytes()).into()),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 在备份目录中创建以时间命名的备份，然后删除超出保留数量的旧备份
+    pub async fn create(&self, store: &SharedStatisticsStore) -> MindPulseResult<BackupFile> {
+        let _guard = self.lock.lock().await;
+
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            error!(message = "Failed to create backup directory", dir = %self.dir.display(), error = ?e);
+            e
+        })?;
+
+        let name = OffsetDateTime::now_utc()
+            .to_offset(self.utc_offset)
+            .format(format_description!(
+                "[year][month][day]-[hour][minute][second]"
+            ))
+            .map_err(|e| MindPulseError::Config(format!("无法生成备份文件名：{}", e)))?;
+        let path = self
+            .dir
+            .join(format!("{}{}{}", BACKUP_PREFIX, name, BACKUP_SUFFIX));
+
+        let file = write_backup(store, &path).await?;
+        self.rotate();
+
+        Ok(file)
+    }
+
+    /// 只保留最近的 `keep` 个备份
+    fn rotate(&self) {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(message = "Failed to list backups", dir = %self.dir.display(), error = ?e);
+                return;
+            }
+        };
+
+        // 文件名中的时间可以按字典序排序
+        let mut backups: Vec<OsString> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .filter(|name| {
+                name.to_str().is_some_and(|name| {
+                    name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX)
+                })
+            })
+            .collect();
+        backups.sort_unstable();
+
+        let expired = backups.len().saturating_sub(self.keep);
+        for name in &backups[..expired] {
+            let path = self.dir.join(name);
+            match std::fs::remove_file(&path) {
+                Ok(()) => info!(message = "Old backup removed", path = %path.display()),
+                Err(e) => {
+                    warn!(message = "Failed to remove old backup", path = %path.display(), error = ?e)
+                }
+            }
+        }
+    }
+
+    /// 按固定间隔在后台备份，失败时记录错误并等待下一次
+    pub fn schedule(
+        self: Arc<Self>,
+        store: SharedStatisticsStore,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        info!(message = "Scheduled backups enabled", interval = ?interval, dir = %self.dir.display(), keep = self.keep);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 启动时不立即备份
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+
+                match self.create(&store).await {
+                    Ok(file) => info!(
+                        message = "Scheduled backup created",
+                        path = %file.path.display(),
+                        size = file.size
+                    ),
+                    Err(e) => error!(message = "Scheduled backup failed", error = ?e),
+                }
+            }
+        })
+    }
+
+    fn authorize(&self, req: &Request) -> MindPulseResult<()> {
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim);
+
+        match (token, &self.token_digest) {
+            (Some(token), Some(expected))
+                if Sha256::digest(token.as_bytes())[..] == expected[..] =>
+            {
+                Ok(())
+            }
+            _ => {
+                warn!(
+                    message = "Rejected unauthorized admin request",
+                    path = req.uri().path()
+                );
+                Err(MindPulseError::Unauthorized)
+            }
+        }
+    }
+}
+
+/// 先写入临时文件再重命名，目录中不会出现不完整的备份
+pub async fn write_backup(
+    store: &SharedStatisticsStore,
+    path: &Path,
+) -> MindPulseResult<BackupFile> {
+    let partial = with_suffix(path, ".partial");
+
+    // VACUUM INTO 要求目标文件不存在
+    remove_if_exists(&partial)?;
+
+    if let Err(e) = store.backup(&partial).await {
+        let _ = remove_if_exists(&partial);
+        return Err(e);
+    }
+
+    std::fs::rename(&partial, path)?;
+    let size = std::fs::metadata(path)?.len();
+
+    info!(message = "Database backup created", path = %path.display(), size);
+
+    Ok(BackupFile {
+        path: path.to_path_buf(),
+        size,
+    })
+}
+
+/// 用备份替换数据库文件，服务需处于停止状态
+///
+/// 先校验备份的结构版本，再取得当前数据库的排他锁确认没有其他进程在使用，然后把当前数据库
+/// （连同 WAL 文件）重命名为 `*.pre-restore` 保留，最后把备份的副本重命名为数据库文件。
+pub async fn restore(db_path: &Path, backup: &Path) -> MindPulseResult<PathBuf> {
+    let version = SqliteStore::inspect(backup).await?;
+    if version != SCHEMA_VERSION {
+        return Err(MindPulseError::Config(format!(
+            "备份 {} 的结构版本为 {}，当前版本为 {}，无法恢复",
+            backup.display(),
+            version,
+            SCHEMA_VERSION
+        )));
+    }
+
+    if let Some(dir) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // 持有锁直到替换完成，服务仍在运行时在这里失败，不改动任何文件
+    let lock = if db_path.exists() {
+        Some(ExclusiveLock::acquire(db_path).await?)
+    } else {
+        None
+    };
+
+    // 复制到数据库所在目录，保证最后一步的重命名是原子的
+    let restoring = with_suffix(db_path, ".restoring");
+    let previous = with_suffix(db_path, ".pre-restore");
+    let swapped = swap(db_path, backup, &restoring, &previous);
+
+    if let Some(lock) = lock {
+        if let Err(e) = lock.release().await {
+            warn!(message = "Failed to release database lock", error = ?e);
+        }
+    }
+
+    if let Err(e) = swapped {
+        let _ = remove_if_exists(&restoring);
+        return Err(e.into());
+    }
+
+    info!(
+        message = "Database restored",
+        backup = %backup.display(),
+        db = %db_path.display(),
+        previous = %previous.display()
+    );
+
+    Ok(previous)
+}
+
+/// 复制备份，把当前数据库移到 `previous`，再把副本移到数据库路径
+fn swap(db_path: &Path, backup: &Path, restoring: &Path, previous: &Path) -> std::io::Result<()> {
+    std::fs::copy(backup, restoring)?;
+    std::fs::File::open(restoring)?.sync_all()?;
+
+    for suffix in ["", "-wal", "-shm"] {
+        let from = with_suffix(db_path, suffix);
+        let to = with_suffix(previous, suffix);
+        match std::fs::rename(&from, &to) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    std::fs::rename(restoring, db_path)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// 管理接口：立即创建一次备份
+#[handler]
+pub async fn create_backup(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    let backups = depot.obtain::<Arc<Backups>>().map_err(|_| {
+        error!(message = "Backup state is missing from depot");
+        MindPulseError::Unavailable("备份服务不可用")
+    })?;
+
+    backups.authorize(req)?;
+
+    let statistics = obtain_statistics(depot)?;
+    let file = backups.create(statistics.store()).await?;
+
+    res.status_code(StatusCode::CREATED);
+    res.render(Json(file));
+
+    Ok(())
+}
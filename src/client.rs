@@ -0,0 +1,183 @@
+use std::{net::IpAddr, str::FromStr, sync::Arc};
+
+use salvo::{Depot, Request};
+
+/// 可信的反向代理，格式为 IP 地址或 CIDR，如 `127.0.0.1`、`10.0.0.0/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl TrustedProxy {
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for TrustedProxy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的代理地址 `{}`，应形如 `127.0.0.1` 或 `10.0.0.0/8`", s);
+
+        let (network, prefix) = match s.trim().split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let network = network
+            .parse::<IpAddr>()
+            .map_err(|_| invalid())?
+            .to_canonical();
+
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(invalid)?,
+            None => max,
+        };
+
+        Ok(Self { network, prefix })
+    }
+}
+
+/// 可信代理列表，通过 `Depot` 注入
+#[derive(Debug, Default)]
+pub struct TrustedProxies(Vec<TrustedProxy>);
+
+impl TrustedProxies {
+    pub fn new(proxies: Vec<TrustedProxy>) -> Self {
+        Self(proxies)
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.0.iter().any(|proxy| proxy.contains(ip))
+    }
+
+    /// 解析客户端地址
+    ///
+    /// 只有对端是可信代理（或通过 Unix 套接字连接）时才读取 `X-Forwarded-For`，并从右向左
+    /// 取第一个不是可信代理的地址；左侧的条目由客户端任意填写，不能使用。其他情况使用对端地址。
+    pub fn client_address(&self, req: &Request) -> String {
+        let peer = req.remote_addr();
+        let peer_ip = peer.ip().map(|ip| ip.to_canonical());
+
+        let peer_trusted = peer.is_unix() || peer_ip.is_some_and(|ip| self.contains(ip));
+        if !peer_trusted {
+            return peer_ip.map(|ip| ip.to_string()).unwrap_or_default();
+        }
+
+        // 多个 `X-Forwarded-For` 头按顺序拼接
+        let forwarded: Vec<&str> = req
+            .headers()
+            .get_all("X-Forwarded-For")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .collect();
+
+        for entry in forwarded.iter().rev() {
+            match entry.parse::<IpAddr>() {
+                Ok(ip) if self.contains(ip) => continue,
+                Ok(ip) => return ip.to_canonical().to_string(),
+                // 由可信代理写入的非 IP 值（如 `unknown`），原样使用
+                Err(_) => return (*entry).to_owned(),
+            }
+        }
+
+        // 所有条目都是可信代理时，最左侧的代理即为来源
+        forwarded
+            .first()
+            .map(|entry| (*entry).to_owned())
+            .or_else(|| peer_ip.map(|ip| ip.to_string()))
+            .unwrap_or_default()
+    }
+}
+
+/// 解析客户端地址，未注入可信代理列表时只使用对端地址
+pub fn client_address(req: &Request, depot: &Depot) -> String {
+    match depot.obtain::<Arc<TrustedProxies>>() {
+        Ok(proxies) => proxies.client_address(req),
+        Err(_) => TrustedProxies::default().client_address(req),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxies(list: &[&str]) -> TrustedProxies {
+        TrustedProxies::new(list.iter().map(|p| p.parse().unwrap()).collect())
+    }
+
+    fn request(peer: &str, forwarded: Option<&str>) -> Request {
+        let mut req = Request::new();
+        *req.remote_addr_mut() = peer.parse::<std::net::SocketAddr>().unwrap().into();
+        if let Some(forwarded) = forwarded {
+            req.headers_mut()
+                .insert("X-Forwarded-For", forwarded.parse().unwrap());
+        }
+        req
+    }
+
+    #[test]
+    fn parse_trusted_proxy() {
+        assert!("127.0.0.1".parse::<TrustedProxy>().is_ok());
+        assert!("10.0.0.0/8".parse::<TrustedProxy>().is_ok());
+        assert!("fd00::/8".parse::<TrustedProxy>().is_ok());
+        assert!("10.0.0.0/33".parse::<TrustedProxy>().is_err());
+        assert!("localhost".parse::<TrustedProxy>().is_err());
+    }
+
+    #[test]
+    fn cidr_contains() {
+        let proxies = proxies(&["10.0.0.0/8", "::1"]);
+        assert!(proxies.contains("10.1.2.3".parse().unwrap()));
+        assert!(!proxies.contains("11.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("::1".parse().unwrap()));
+        // IPv4 映射的 IPv6 地址按 IPv4 处理
+        assert!(proxies.contains("::ffff:10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_header() {
+        let proxies = proxies(&["127.0.0.1"]);
+        let req = request("203.0.113.7:5000", Some("10.0.0.1"));
+        assert_eq!(proxies.client_address(&req), "203.0.113.7");
+    }
+
+    #[test]
+    fn trusted_peer_uses_rightmost_untrusted_entry() {
+        let proxies = proxies(&["127.0.0.1", "10.0.0.0/8"]);
+        // 客户端伪造了第一项，代理追加了真实地址
+        let req = request("127.0.0.1:5000", Some("1.2.3.4, 198.51.100.9, 10.0.0.2"));
+        assert_eq!(proxies.client_address(&req), "198.51.100.9");
+    }
+
+    #[test]
+    fn trusted_peer_without_header_uses_peer() {
+        let proxies = proxies(&["127.0.0.1"]);
+        let req = request("127.0.0.1:5000", None);
+        assert_eq!(proxies.client_address(&req), "127.0.0.1");
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::backup::{self, write_backup, Backups};
+use crate::config::Config;
+use crate::error::{MindPulseError, MindPulseResult};
+use crate::scale::{get_scale_info_by_id, serialize_scale_by_id, validate_scales, LIST};
+use crate::statistics::{connect_statistics_store, AccessMode, SharedStatisticsStore};
+
+/// 子命令，未指定时默认为 `serve`
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// 启动 HTTP 服务
+    Serve,
+    /// 创建或升级数据表
+    Migrate,
+    /// 检查所有量表的数据是否完整
+    ValidateScales,
+    /// 导出每个量表的完成次数
+    ExportStatistics {
+        /// 输出格式
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// 输出文件，默认输出到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 查看完成次数
+    Stats {
+        /// 只查看指定量表
+        #[arg(long)]
+        id: Option<u16>,
+    },
+    /// 立即备份数据库，默认写入备份目录并删除超出保留数量的旧备份
+    Backup {
+        /// 备份文件路径，指定时不做轮换
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 用备份替换 SQLite 数据库，需先停止服务，数据库仍在使用时拒绝执行
+    Restore {
+        /// 备份文件
+        file: PathBuf,
+    },
+    /// 输出量表的完整 JSON 数据
+    DumpScale {
+        /// 量表 ID
+        id: u16,
+        /// 格式化输出
+        #[arg(long)]
+        pretty: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// 一个量表的完成次数
+#[derive(Debug, Serialize)]
+struct StatisticsRow {
+    id: u16,
+    slug: &'static str,
+    name: &'static str,
+    count: u64,
+}
+
+/// 连接数据库并创建数据表
+async fn connect(config: &Config) -> MindPulseResult<SharedStatisticsStore> {
+    let store = connect_statistics_store(&config.database, AccessMode::ReadWrite).await?;
+    store.create_statistics_table().await?;
+    Ok(store)
+}
+
+/// 以只读方式连接已有的数据库，不创建文件和数据表
+async fn connect_read_only(config: &Config) -> MindPulseResult<SharedStatisticsStore> {
+    connect_statistics_store(&config.database, AccessMode::ReadOnly).await
+}
+
+/// 创建或升级数据表
+pub async fn migrate(config: &Config) -> MindPulseResult<ExitCode> {
+    let store = connect(config).await?;
+    store.close().await;
+
+    println!("数据表已就绪（{}）", store.backend());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 检查量表数据，有问题时返回失败的退出码
+pub fn validate() -> ExitCode {
+    let problems = validate_scales();
+
+    if problems.is_empty() {
+        println!("{} 个量表全部通过检查", LIST.len());
+        return ExitCode::SUCCESS;
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    println!("发现 {} 个问题", problems.len());
+
+    ExitCode::FAILURE
+}
+
+/// 查询完成次数，`id` 为空时返回所有量表
+async fn query_rows(config: &Config, id: Option<u16>) -> MindPulseResult<Vec<StatisticsRow>> {
+    if let Some(id) = id {
+        // 先验证 ID，避免连接数据库后才报错
+        get_scale_info_by_id(id)?;
+    }
+
+    let store = connect_read_only(config).await?;
+    let counts = store.query_statistics_counts().await;
+    store.close().await;
+    let counts = counts?;
+
+    Ok(LIST
+        .iter()
+        .filter(|p| id.is_none_or(|id| p.id == id))
+        .map(|p| StatisticsRow {
+            id: p.id,
+            slug: p.slug,
+            name: p.name,
+            count: counts
+                .iter()
+                .find(|(scale, _)| *scale == p.id)
+                .map(|(_, count)| *count)
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// 按指定格式导出完成次数
+pub async fn export_statistics(
+    config: &Config,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> MindPulseResult<ExitCode> {
+    let rows = query_rows(config, None).await?;
+
+    let content = match format {
+        ExportFormat::Csv => {
+            let mut csv = String::from("id,slug,name,count\n");
+            for row in &rows {
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    row.id,
+                    csv_field(row.slug),
+                    csv_field(row.name),
+                    row.count
+                ));
+            }
+            csv.into_bytes()
+        }
+        ExportFormat::Json => serde_json::to_vec_pretty(&rows)?,
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            eprintln!("已导出 {} 条记录到 {}", rows.len(), path.display());
+        }
+        None => io::stdout().write_all(&content)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 以表格形式输出完成次数
+pub async fn stats(config: &Config, id: Option<u16>) -> MindPulseResult<ExitCode> {
+    let rows = query_rows(config, id).await?;
+
+    for row in &rows {
+        println!(
+            "{:>3}  {:<24}  {:>8}  {}",
+            row.id, row.slug, row.count, row.name
+        );
+    }
+
+    if id.is_none() {
+        println!("合计 {}", rows.iter().map(|r| r.count).sum::<u64>());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 备份数据库
+pub async fn backup(config: &Config, output: Option<PathBuf>) -> MindPulseResult<ExitCode> {
+    let store = connect_read_only(config).await?;
+
+    let result = match output {
+        Some(path) => write_backup(&store, &path).await,
+        None => {
+            Backups::new(&config.backup, &config.admin, config.utc_offset.0)
+                .create(&store)
+                .await
+        }
+    };
+    store.close().await;
+    let file = result?;
+
+    println!("已备份到 {}（{} 字节）", file.path.display(), file.size);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 校验备份的结构版本后替换数据库文件
+pub async fn restore(config: &Config, file: PathBuf) -> MindPulseResult<ExitCode> {
+    let Some(db_path) = config.database.sqlite_path() else {
+        return Err(MindPulseError::Config(
+            "只有 SQLite 数据库支持恢复".to_owned(),
+        ));
+    };
+
+    let previous = backup::restore(&db_path, &file).await?;
+
+    println!("已从 {} 恢复到 {}", file.display(), db_path.display());
+    if previous.exists() {
+        println!("原数据库已保存为 {}", previous.display());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 输出量表的完整 JSON 数据
+pub fn dump_scale(id: u16, pretty: bool) -> MindPulseResult<ExitCode> {
+    let mut json = serialize_scale_by_id(id)?;
+
+    if pretty {
+        let value: serde_json::Value = serde_json::from_slice(&json)?;
+        json = serde_json::to_vec_pretty(&value)?;
+    }
+    json.push(b'\n');
+
+    io::stdout().write_all(&json)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 包含逗号、引号或换行时加引号转义
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
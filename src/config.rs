@@ -0,0 +1,1115 @@
+use std::{
+    env,
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+use time::{macros::format_description, UtcOffset};
+use tracing_subscriber::EnvFilter;
+
+use crate::client::TrustedProxy;
+use crate::command::Command;
+use crate::error::{MindPulseError, MindPulseResult};
+use crate::rate_limit::RateLimitRule;
+
+/// 未指定配置文件时尝试读取的路径，不存在则忽略
+const DEFAULT_CONFIG_FILE: &str = "./mind_pulse.toml";
+
+/// 管理接口令牌的最小长度
+const MIN_ADMIN_TOKEN_LEN: usize = 16;
+
+/// 命令行参数，优先级最高
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "心灵脉冲服务器",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// 监听端口，兼容旧用法 `./server 9999`
+    #[arg(value_name = "PORT")]
+    legacy_port: Option<u16>,
+
+    /// 配置文件路径，默认读取 `./mind_pulse.toml`（不存在时忽略）
+    #[arg(short, long, global = true, env = "MIND_PULSE_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// 监听地址
+    #[arg(long, global = true)]
+    bind: Option<IpAddr>,
+
+    /// 监听端口
+    #[arg(short, long, global = true)]
+    port: Option<u16>,
+
+    /// 监听 Unix 套接字，设置后忽略 `--bind` 和 `--port`
+    #[arg(long, global = true)]
+    socket: Option<PathBuf>,
+
+    /// 数据库 URL，支持 `sqlite://` 和 `postgres://`，优先于 `--db-path`
+    #[arg(long, global = true)]
+    database_url: Option<String>,
+
+    /// SQLite 数据库文件路径
+    #[arg(long, global = true)]
+    db_path: Option<String>,
+
+    /// 数据库连接池大小
+    #[arg(long, global = true)]
+    pool_size: Option<u32>,
+
+    /// 日志目录
+    #[arg(long, global = true)]
+    log_dir: Option<PathBuf>,
+
+    /// 日志格式：`pretty` 或 `json`
+    #[arg(long, global = true)]
+    log_format: Option<LogFormat>,
+
+    /// 日志过滤指令，语法同 `RUST_LOG`，如 `server=debug`
+    #[arg(long, global = true)]
+    log_filter: Option<String>,
+
+    /// 日志和完成时间使用的时区偏移，如 `+08:00`
+    #[arg(long, global = true)]
+    utc_offset: Option<TimeOffset>,
+}
+
+/// 时区偏移，格式为 `+08:00`
+#[derive(Debug, Clone, Copy)]
+pub struct TimeOffset(pub UtcOffset);
+
+impl Default for TimeOffset {
+    fn default() -> Self {
+        Self(time::macros::offset!(+8))
+    }
+}
+
+impl FromStr for TimeOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UtcOffset::parse(
+            s.trim(),
+            format_description!("[offset_hour sign:mandatory]:[offset_minute]"),
+        )
+        .map(Self)
+        .map_err(|_| format!("无效的时区偏移 `{}`，应形如 `+08:00`", s))
+    }
+}
+
+/// Unix 套接字文件的权限，格式为八进制，如 `660`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketMode(pub u32);
+
+impl Default for SocketMode {
+    fn default() -> Self {
+        Self(0o660)
+    }
+}
+
+impl FromStr for SocketMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.trim().trim_start_matches("0o");
+        match u32::from_str_radix(digits, 8) {
+            Ok(mode) if mode <= 0o777 => Ok(Self(mode)),
+            _ => Err(format!("无效的套接字权限 `{}`，应形如 `660`", s)),
+        }
+    }
+}
+
+impl Display for SocketMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:03o}", self.0)
+    }
+}
+
+/// 运行时配置
+///
+/// 依次读取默认值、配置文件、环境变量和命令行参数，后者覆盖前者。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 日志和完成时间使用的时区偏移
+    #[serde(deserialize_with = "from_str")]
+    pub utc_offset: TimeOffset,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub log: LogConfig,
+    pub tls: TlsSettings,
+    pub backup: BackupSettings,
+    pub admin: AdminSettings,
+    pub otel: OtelSettings,
+    pub health: HealthSettings,
+    pub cors: CorsSettings,
+    pub rate_limit: RateLimitSettings,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+    /// Unix 套接字路径，设置后代替 `bind` 和 `port`
+    pub socket: Option<PathBuf>,
+    /// 套接字文件的权限
+    #[serde(deserialize_with = "from_str")]
+    pub socket_mode: SocketMode,
+    /// 关闭时等待进行中的请求完成的最长时间（秒），超时后强制关闭连接
+    pub shutdown_timeout: u64,
+    /// 可信的反向代理，只有来自这些地址的 `X-Forwarded-For` 才会被采用
+    #[serde(deserialize_with = "from_str_list")]
+    pub trusted_proxies: Vec<TrustedProxy>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 4819,
+            socket: None,
+            socket_mode: SocketMode::default(),
+            shutdown_timeout: 30,
+            trusted_proxies: ["127.0.0.1", "::1"]
+                .iter()
+                .map(|proxy| proxy.parse().expect("默认代理地址有效"))
+                .collect(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// 数据库 URL，设置后优先于 `path`
+    pub url: Option<String>,
+    /// SQLite 数据库文件路径
+    pub path: String,
+    /// 连接池大小
+    pub max_connections: u32,
+    /// SQLite 数据库被锁定时的最长等待时间（毫秒）
+    pub busy_timeout_ms: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            path: "./mind_pulse.sqlite".to_owned(),
+            max_connections: 5,
+            busy_timeout_ms: 5000,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    pub fn busy_timeout(&self) -> Duration {
+        Duration::from_millis(self.busy_timeout_ms)
+    }
+
+    /// SQLite 数据库文件的路径，使用其他后端时为空
+    pub fn sqlite_path(&self) -> Option<PathBuf> {
+        match self.url.as_deref() {
+            None => Some(PathBuf::from(&self.path)),
+            Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => None,
+            Some(url) => {
+                let path = url
+                    .strip_prefix("sqlite://")
+                    .or_else(|| url.strip_prefix("sqlite:"))
+                    .unwrap_or(url);
+                let path = path.split_once('?').map_or(path, |(path, _)| path);
+                Some(PathBuf::from(path))
+            }
+        }
+    }
+}
+
+/// 日志按时间轮换的周期
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Daily,
+    Hourly,
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "daily" => Ok(Self::Daily),
+            "hourly" => Ok(Self::Hourly),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "无效的轮换周期 `{}`，可选 daily、hourly 或 never",
+                s
+            )),
+        }
+    }
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// 便于阅读的单行文本
+    Pretty,
+    /// 每行一个 JSON 对象，便于日志系统采集
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("无效的日志格式 `{}`，可选 pretty 或 json", s)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// 日志目录
+    pub dir: PathBuf,
+    /// 日志文件名，按时间轮换时追加日期，按大小轮换时再追加序号
+    pub file_name: String,
+    /// 按时间轮换的周期
+    #[serde(deserialize_with = "from_str")]
+    pub rotation: LogRotation,
+    /// 单个文件的最大大小（MB），超过后轮换，为 0 时不按大小轮换
+    pub max_size_mb: u64,
+    /// 保留的已轮换文件数量，为 0 时不限制
+    pub max_files: usize,
+    /// 已轮换文件的保留天数，为 0 时不限制
+    pub max_age_days: u64,
+    /// 使用 gzip 压缩已轮换的文件
+    pub compress: bool,
+    /// 输出格式
+    #[serde(deserialize_with = "from_str")]
+    pub format: LogFormat,
+    /// 过滤指令，语法同 `RUST_LOG`
+    pub filter: String,
+    /// 同时输出到标准错误
+    pub stderr: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        // 默认值与之前按编译模式区分的行为一致
+        let debug = cfg!(debug_assertions);
+
+        Self {
+            dir: PathBuf::from("./log"),
+            file_name: "confidant-server.log".to_owned(),
+            rotation: LogRotation::Daily,
+            max_size_mb: 0,
+            max_files: 30,
+            max_age_days: 0,
+            compress: false,
+            format: if debug {
+                LogFormat::Pretty
+            } else {
+                LogFormat::Json
+            },
+            filter: if debug { "server=trace" } else { "server=warn" }.to_owned(),
+            stderr: debug,
+        }
+    }
+}
+
+/// TLS 配置，`cert` 和 `key` 都设置时启用 HTTPS
+///
+/// 收到 SIGHUP 时重新读取证书和私钥，读取失败时继续使用原来的证书。
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    /// PEM 格式的证书链
+    pub cert: Option<PathBuf>,
+    /// PEM 格式的私钥
+    pub key: Option<PathBuf>,
+    /// 设置后在该端口监听 HTTP，并重定向到 HTTPS
+    pub redirect_port: Option<u16>,
+}
+
+impl TlsSettings {
+    /// 证书和私钥的路径，未启用 TLS 时为空
+    pub fn files(&self) -> Option<(&Path, &Path)> {
+        self.cert.as_deref().zip(self.key.as_deref())
+    }
+}
+
+/// 备份配置
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupSettings {
+    /// 备份目录
+    pub dir: PathBuf,
+    /// 保留最近的备份数量，更早的自动删除
+    pub keep: usize,
+    /// 定时备份的间隔（小时），为空时不定时备份
+    pub interval_hours: Option<u64>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("./backup"),
+            keep: 7,
+            interval_hours: None,
+        }
+    }
+}
+
+/// 管理接口配置
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminSettings {
+    /// 访问令牌，请求需携带 `Authorization: Bearer <token>`，为空时不开放管理接口
+    pub token: Option<String>,
+}
+
+impl std::fmt::Debug for AdminSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // 不输出令牌
+        f.debug_struct("AdminSettings")
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// OTLP 导出协议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtelProtocol {
+    Grpc,
+    Http,
+}
+
+impl FromStr for OtelProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "grpc" => Ok(Self::Grpc),
+            "http" | "http/protobuf" => Ok(Self::Http),
+            _ => Err(format!("无效的 OTLP 协议 `{}`，可选 grpc 或 http", s)),
+        }
+    }
+}
+
+/// OpenTelemetry 链路导出配置，`endpoint` 为空时不导出
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OtelSettings {
+    /// OTLP collector 地址，如 gRPC `http://localhost:4317`、HTTP `http://localhost:4318`
+    pub endpoint: Option<String>,
+    /// 导出协议
+    #[serde(deserialize_with = "from_str")]
+    pub protocol: OtelProtocol,
+    /// 上报的服务名称
+    pub service_name: String,
+    /// 采样比例，0 到 1，请求带有上游链路时沿用上游的采样决定
+    pub sample_ratio: f64,
+    /// 导出哪些 span，语法同 `RUST_LOG`，与日志的过滤指令相互独立
+    pub filter: String,
+    /// 指标的推送间隔（秒）
+    pub metric_interval_secs: u64,
+}
+
+impl Default for OtelSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            protocol: OtelProtocol::Grpc,
+            service_name: "mind-pulse-server".to_owned(),
+            sample_ratio: 1.0,
+            filter: "server=info".to_owned(),
+            metric_interval_secs: 60,
+        }
+    }
+}
+
+/// 就绪检查配置
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthSettings {
+    /// 数据库所在磁盘的最小可用空间（MB），低于该值时未就绪
+    pub min_free_disk_mb: u64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            min_free_disk_mb: 100,
+        }
+    }
+}
+
+/// CORS 配置，`origins` 为空时不启用
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    /// 允许的来源，支持 `*` 和 `https://*.example.com`
+    pub origins: Vec<String>,
+    /// 允许的请求方法
+    pub methods: Vec<String>,
+    /// 允许的请求头
+    pub headers: Vec<String>,
+    /// 预检结果缓存时间（秒）
+    pub max_age: u64,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: vec!["GET".to_owned(), "POST".to_owned()],
+            headers: [
+                "content-type",
+                "accept-language",
+                "if-none-match",
+                "x-request-id",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+            max_age: 86400,
+        }
+    }
+}
+
+/// 限流配置，规则格式为 `次数/秒数`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSettings {
+    /// 提交完成记录
+    #[serde(deserialize_with = "from_str")]
+    pub completions: RateLimitRule,
+    /// 提交答案计分
+    #[serde(deserialize_with = "from_str")]
+    pub results: RateLimitRule,
+    /// 限流状态文件，设置后在关闭时保存、启动时恢复
+    pub state: Option<PathBuf>,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            completions: RateLimitRule::new(10, Duration::from_secs(60)),
+            results: RateLimitRule::new(30, Duration::from_secs(60)),
+            state: None,
+        }
+    }
+}
+
+impl Config {
+    /// 按优先级合并所有来源的配置并校验
+    pub fn load(cli: &Cli) -> MindPulseResult<Self> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.apply_cli(cli);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> MindPulseResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            MindPulseError::Config(format!("无法读取配置文件 {}：{}", path.display(), e))
+        })?;
+
+        toml::from_str(&content).map_err(|e| {
+            MindPulseError::Config(format!("配置文件 {} 格式错误：{}", path.display(), e))
+        })
+    }
+
+    fn apply_env(&mut self) -> MindPulseResult<()> {
+        if let Some(v) = env_parse("MIND_PULSE_UTC_OFFSET")? {
+            self.utc_offset = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_BIND")? {
+            self.server.bind = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_PORT")? {
+            self.server.port = v;
+        }
+        if let Ok(v) = env::var("MIND_PULSE_SOCKET") {
+            self.server.socket = Some(PathBuf::from(v));
+        }
+        if let Some(v) = env_parse("MIND_PULSE_SOCKET_MODE")? {
+            self.server.socket_mode = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_SHUTDOWN_TIMEOUT")? {
+            self.server.shutdown_timeout = v;
+        }
+        if let Some(v) = env_parse_list("MIND_PULSE_TRUSTED_PROXIES")? {
+            self.server.trusted_proxies = v;
+        }
+
+        if let Ok(v) = env::var("DATABASE_URL") {
+            self.database.url = Some(v);
+        }
+        if let Ok(v) = env::var("MIND_PULSE_DB_PATH") {
+            self.database.path = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_DB_POOL_SIZE")? {
+            self.database.max_connections = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_DB_BUSY_TIMEOUT_MS")? {
+            self.database.busy_timeout_ms = v;
+        }
+
+        if let Ok(v) = env::var("MIND_PULSE_LOG_DIR") {
+            self.log.dir = PathBuf::from(v);
+        }
+        if let Ok(v) = env::var("MIND_PULSE_LOG_FILE_NAME") {
+            self.log.file_name = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_ROTATION")? {
+            self.log.rotation = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_MAX_SIZE_MB")? {
+            self.log.max_size_mb = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_MAX_FILES")? {
+            self.log.max_files = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_MAX_AGE_DAYS")? {
+            self.log.max_age_days = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_COMPRESS")? {
+            self.log.compress = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_FORMAT")? {
+            self.log.format = v;
+        }
+        if let Ok(v) = env::var("MIND_PULSE_LOG_FILTER") {
+            self.log.filter = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_LOG_STDERR")? {
+            self.log.stderr = v;
+        }
+
+        if let Ok(v) = env::var("MIND_PULSE_TLS_CERT") {
+            self.tls.cert = Some(PathBuf::from(v));
+        }
+        if let Ok(v) = env::var("MIND_PULSE_TLS_KEY") {
+            self.tls.key = Some(PathBuf::from(v));
+        }
+        if let Some(v) = env_parse("MIND_PULSE_TLS_REDIRECT_PORT")? {
+            self.tls.redirect_port = Some(v);
+        }
+
+        if let Ok(v) = env::var("MIND_PULSE_BACKUP_DIR") {
+            self.backup.dir = PathBuf::from(v);
+        }
+        if let Some(v) = env_parse("MIND_PULSE_BACKUP_KEEP")? {
+            self.backup.keep = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_BACKUP_INTERVAL_HOURS")? {
+            self.backup.interval_hours = Some(v);
+        }
+        if let Ok(v) = env::var("MIND_PULSE_ADMIN_TOKEN") {
+            self.admin.token = Some(v);
+        }
+
+        if let Ok(v) = env::var("MIND_PULSE_OTEL_ENDPOINT") {
+            self.otel.endpoint = Some(v);
+        }
+        if let Some(v) = env_parse("MIND_PULSE_OTEL_PROTOCOL")? {
+            self.otel.protocol = v;
+        }
+        if let Ok(v) = env::var("MIND_PULSE_OTEL_SERVICE_NAME") {
+            self.otel.service_name = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_OTEL_SAMPLE_RATIO")? {
+            self.otel.sample_ratio = v;
+        }
+        if let Ok(v) = env::var("MIND_PULSE_OTEL_FILTER") {
+            self.otel.filter = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_OTEL_METRIC_INTERVAL_SECS")? {
+            self.otel.metric_interval_secs = v;
+        }
+
+        if let Some(v) = env_parse("MIND_PULSE_MIN_FREE_DISK_MB")? {
+            self.health.min_free_disk_mb = v;
+        }
+
+        if let Ok(v) = env::var("MIND_PULSE_CORS_ORIGINS") {
+            self.cors.origins = env_list(&v);
+        }
+        if let Ok(v) = env::var("MIND_PULSE_CORS_METHODS") {
+            self.cors.methods = env_list(&v);
+        }
+        if let Ok(v) = env::var("MIND_PULSE_CORS_HEADERS") {
+            self.cors.headers = env_list(&v);
+        }
+        if let Some(v) = env_parse("MIND_PULSE_CORS_MAX_AGE")? {
+            self.cors.max_age = v;
+        }
+
+        if let Some(v) = env_parse("MIND_PULSE_RATE_LIMIT_COMPLETIONS")? {
+            self.rate_limit.completions = v;
+        }
+        if let Some(v) = env_parse("MIND_PULSE_RATE_LIMIT_RESULTS")? {
+            self.rate_limit.results = v;
+        }
+        if let Ok(v) = env::var("MIND_PULSE_RATE_LIMIT_STATE") {
+            self.rate_limit.state = Some(PathBuf::from(v));
+        }
+
+        Ok(())
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(v) = cli.utc_offset {
+            self.utc_offset = v;
+        }
+        if let Some(v) = cli.bind {
+            self.server.bind = v;
+        }
+        if let Some(v) = cli.port.or(cli.legacy_port) {
+            self.server.port = v;
+        }
+        if let Some(v) = &cli.socket {
+            self.server.socket = Some(v.clone());
+        }
+        if let Some(v) = &cli.database_url {
+            self.database.url = Some(v.clone());
+        }
+        if let Some(v) = &cli.db_path {
+            self.database.path = v.clone();
+        }
+        if let Some(v) = cli.pool_size {
+            self.database.max_connections = v;
+        }
+        if let Some(v) = &cli.log_dir {
+            self.log.dir = v.clone();
+        }
+        if let Some(v) = cli.log_format {
+            self.log.format = v;
+        }
+        if let Some(v) = &cli.log_filter {
+            self.log.filter = v.clone();
+        }
+    }
+
+    fn validate(&self) -> MindPulseResult<()> {
+        if self.server.port == 0 {
+            return Err(MindPulseError::Config("端口不能为 0".to_owned()));
+        }
+
+        if let Some(socket) = &self.server.socket {
+            if cfg!(not(unix)) {
+                return Err(MindPulseError::Config(
+                    "当前平台不支持 Unix 套接字".to_owned(),
+                ));
+            }
+            if socket.as_os_str().is_empty() {
+                return Err(MindPulseError::Config("套接字路径不能为空".to_owned()));
+            }
+            if self.tls.files().is_some() {
+                return Err(MindPulseError::Config(
+                    "监听 Unix 套接字时不支持 TLS，请由反向代理处理 HTTPS".to_owned(),
+                ));
+            }
+        }
+
+        if !(1..=100).contains(&self.database.max_connections) {
+            return Err(MindPulseError::Config(format!(
+                "连接池大小应在 1 到 100 之间，当前为 {}",
+                self.database.max_connections
+            )));
+        }
+
+        if self.database.url.as_deref().is_some_and(str::is_empty)
+            || (self.database.url.is_none() && self.database.path.trim().is_empty())
+        {
+            return Err(MindPulseError::Config("数据库地址不能为空".to_owned()));
+        }
+
+        if self.log.dir.as_os_str().is_empty() {
+            return Err(MindPulseError::Config("日志目录不能为空".to_owned()));
+        }
+
+        if self.log.file_name.trim().is_empty() || self.log.file_name.contains(['/', '\\']) {
+            return Err(MindPulseError::Config(format!(
+                "无效的日志文件名 `{}`",
+                self.log.file_name
+            )));
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.log.filter) {
+            return Err(MindPulseError::Config(format!(
+                "无效的日志过滤指令 `{}`：{}",
+                self.log.filter, e
+            )));
+        }
+
+        if self.backup.dir.as_os_str().is_empty() {
+            return Err(MindPulseError::Config("备份目录不能为空".to_owned()));
+        }
+
+        if self.backup.keep == 0 {
+            return Err(MindPulseError::Config("至少需要保留 1 个备份".to_owned()));
+        }
+
+        if self.backup.interval_hours == Some(0) {
+            return Err(MindPulseError::Config("定时备份的间隔不能为 0".to_owned()));
+        }
+
+        if self
+            .admin
+            .token
+            .as_ref()
+            .is_some_and(|token| token.trim().len() < MIN_ADMIN_TOKEN_LEN)
+        {
+            return Err(MindPulseError::Config(format!(
+                "管理接口的令牌至少需要 {} 个字符",
+                MIN_ADMIN_TOKEN_LEN
+            )));
+        }
+
+        if let Some(endpoint) = &self.otel.endpoint {
+            if endpoint.trim().is_empty() {
+                return Err(MindPulseError::Config("OTLP 地址不能为空".to_owned()));
+            }
+            if cfg!(not(feature = "otel")) {
+                return Err(MindPulseError::Config(
+                    "未启用 OpenTelemetry 支持，请使用 `--features otel` 重新编译".to_owned(),
+                ));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.otel.sample_ratio) {
+            return Err(MindPulseError::Config(format!(
+                "采样比例应在 0 到 1 之间，当前为 {}",
+                self.otel.sample_ratio
+            )));
+        }
+
+        if self.otel.service_name.trim().is_empty() {
+            return Err(MindPulseError::Config("服务名称不能为空".to_owned()));
+        }
+
+        if self.otel.metric_interval_secs == 0 {
+            return Err(MindPulseError::Config("指标推送间隔至少为 1 秒".to_owned()));
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.otel.filter) {
+            return Err(MindPulseError::Config(format!(
+                "无效的链路过滤指令 `{}`：{}",
+                self.otel.filter, e
+            )));
+        }
+
+        if self.tls.cert.is_some() != self.tls.key.is_some() {
+            return Err(MindPulseError::Config(
+                "TLS 证书和私钥需要同时设置".to_owned(),
+            ));
+        }
+
+        if let Some(port) = self.tls.redirect_port {
+            if self.tls.files().is_none() {
+                return Err(MindPulseError::Config(
+                    "设置重定向端口时需要同时配置 TLS 证书和私钥".to_owned(),
+                ));
+            }
+            if port == 0 || port == self.server.port {
+                return Err(MindPulseError::Config(format!(
+                    "重定向端口 {} 无效，不能为 0 或与服务端口相同",
+                    port
+                )));
+            }
+        }
+
+        if cfg!(not(feature = "tls")) && self.tls.files().is_some() {
+            return Err(MindPulseError::Config(
+                "未启用 TLS 支持，请使用 `--features tls` 重新编译".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// 读取并解析环境变量，格式错误时指明变量名
+fn env_parse<T>(key: &str) -> MindPulseResult<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(key) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|e| {
+            MindPulseError::Config(format!("环境变量 {} 的值 `{}` 无效：{}", key, value, e))
+        }),
+        Err(_) => Ok(None),
+    }
+}
+
+/// 读取并解析逗号分隔的环境变量，任一项格式错误时指明变量名
+fn env_parse_list<T>(key: &str) -> MindPulseResult<Option<Vec<T>>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(key) {
+        Ok(value) => env_list(&value)
+            .iter()
+            .map(|item| item.parse())
+            .collect::<Result<Vec<T>, _>>()
+            .map(Some)
+            .map_err(|e| {
+                MindPulseError::Config(format!("环境变量 {} 的值 `{}` 无效：{}", key, value, e))
+            }),
+        Err(_) => Ok(None),
+    }
+}
+
+/// 逗号分隔的列表
+fn env_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+fn from_str_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| value.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// 环境变量是进程级的，读写环境变量的测试需要串行执行
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn config_file(content: &str) -> NamedTempFile {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), content).unwrap();
+        file
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::parse_from(std::iter::once("server").chain(args.iter().copied()))
+    }
+
+    /// 设置环境变量后加载配置，结束时清除
+    fn load_with_env(vars: &[(&str, &str)], cli: &Cli) -> MindPulseResult<Config> {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, value) in vars {
+            env::set_var(key, value);
+        }
+        let config = Config::load(cli);
+        for (key, _) in vars {
+            env::remove_var(key);
+        }
+        config
+    }
+
+    #[test]
+    fn cli_overrides_env_overrides_file() {
+        let file = config_file(
+            r#"
+            [server]
+            port = 1000
+            shutdown_timeout = 5
+
+            [database]
+            path = "file.sqlite"
+            max_connections = 2
+
+            [log]
+            filter = "server=info"
+            "#,
+        );
+        let path = file.path().to_str().unwrap();
+
+        let config = load_with_env(
+            &[
+                ("MIND_PULSE_PORT", "2000"),
+                ("MIND_PULSE_DB_POOL_SIZE", "3"),
+                ("MIND_PULSE_LOG_FILTER", "server=debug"),
+            ],
+            &cli(&["--config", path, "--port", "3000", "--pool-size", "4"]),
+        )
+        .unwrap();
+
+        // 命令行优先于环境变量
+        assert_eq!(config.server.port, 3000);
+        assert_eq!(config.database.max_connections, 4);
+        // 环境变量优先于配置文件
+        assert_eq!(config.log.filter, "server=debug");
+        // 只在配置文件中设置的值
+        assert_eq!(config.server.shutdown_timeout, 5);
+        assert_eq!(config.database.path, "file.sqlite");
+        // 都未设置时使用默认值
+        assert_eq!(config.database.busy_timeout_ms, 5000);
+
+        // 旧用法的端口参数同样优先于环境变量
+        let config = load_with_env(
+            &[("MIND_PULSE_PORT", "2000")],
+            &cli(&["--config", path, "5000"]),
+        )
+        .unwrap();
+        assert_eq!(config.server.port, 5000);
+    }
+
+    #[test]
+    fn rejects_invalid_sources() {
+        let file = config_file("[server]\nunknown = 1\n");
+        let path = file.path().to_str().unwrap();
+        assert!(load_with_env(&[], &cli(&["--config", path])).is_err());
+
+        let file = config_file("[rate_limit]\ncompletions = \"0/60\"\n");
+        let path = file.path().to_str().unwrap();
+        assert!(load_with_env(&[], &cli(&["--config", path])).is_err());
+
+        let file = config_file("");
+        let path = file.path().to_str().unwrap();
+        for value in ["10", "10/0", "ten/60"] {
+            let result = load_with_env(
+                &[("MIND_PULSE_RATE_LIMIT_RESULTS", value)],
+                &cli(&["--config", path]),
+            );
+            assert!(
+                matches!(&result, Err(MindPulseError::Config(message)) if message.contains("MIND_PULSE_RATE_LIMIT_RESULTS")),
+                "{}",
+                value
+            );
+        }
+        assert!(load_with_env(&[("MIND_PULSE_PORT", "port")], &cli(&["--config", path])).is_err());
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_values() {
+        type Update = fn(&mut Config);
+
+        let cases: &[(&str, Update)] = &[
+            ("port", |c| c.server.port = 0),
+            ("empty socket", |c| c.server.socket = Some(PathBuf::new())),
+            ("socket with tls", |c| {
+                c.server.socket = Some(PathBuf::from("/tmp/mind_pulse.sock"));
+                c.tls.cert = Some(PathBuf::from("cert.pem"));
+                c.tls.key = Some(PathBuf::from("key.pem"));
+            }),
+            ("empty pool", |c| c.database.max_connections = 0),
+            ("large pool", |c| c.database.max_connections = 101),
+            ("empty url", |c| c.database.url = Some(String::new())),
+            ("empty path", |c| c.database.path = " ".to_owned()),
+            ("log dir", |c| c.log.dir = PathBuf::new()),
+            ("empty log file", |c| c.log.file_name = " ".to_owned()),
+            ("nested log file", |c| {
+                c.log.file_name = "a/b.log".to_owned()
+            }),
+            ("log filter", |c| c.log.filter = "server=loud".to_owned()),
+            ("backup dir", |c| c.backup.dir = PathBuf::new()),
+            ("backup keep", |c| c.backup.keep = 0),
+            ("backup interval", |c| c.backup.interval_hours = Some(0)),
+            ("admin token", |c| c.admin.token = Some("short".to_owned())),
+            ("otel endpoint", |c| c.otel.endpoint = Some(" ".to_owned())),
+            ("sample ratio", |c| c.otel.sample_ratio = 1.5),
+            ("service name", |c| c.otel.service_name = " ".to_owned()),
+            ("metric interval", |c| c.otel.metric_interval_secs = 0),
+            ("otel filter", |c| c.otel.filter = "server=loud".to_owned()),
+            ("cert without key", |c| {
+                c.tls.cert = Some(PathBuf::from("cert.pem"))
+            }),
+            ("key without cert", |c| {
+                c.tls.key = Some(PathBuf::from("key.pem"))
+            }),
+            ("redirect without tls", |c| c.tls.redirect_port = Some(80)),
+            ("redirect to zero", |c| {
+                c.tls.cert = Some(PathBuf::from("cert.pem"));
+                c.tls.key = Some(PathBuf::from("key.pem"));
+                c.tls.redirect_port = Some(0);
+            }),
+            ("redirect to server port", |c| {
+                c.tls.cert = Some(PathBuf::from("cert.pem"));
+                c.tls.key = Some(PathBuf::from("key.pem"));
+                c.tls.redirect_port = Some(c.server.port);
+            }),
+        ];
+
+        for (name, update) in cases {
+            let mut config = Config::default();
+            update(&mut config);
+            assert!(
+                matches!(config.validate(), Err(MindPulseError::Config(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn validate_rejects_disabled_features() {
+        let mut config = Config::default();
+        config.otel.endpoint = Some("http://localhost:4317".to_owned());
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "otel"));
+
+        let mut config = Config::default();
+        config.tls.cert = Some(PathBuf::from("cert.pem"));
+        config.tls.key = Some(PathBuf::from("key.pem"));
+        assert_eq!(config.validate().is_ok(), cfg!(feature = "tls"));
+    }
+}
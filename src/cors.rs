@@ -0,0 +1,159 @@
+use salvo::{
+    cors::{AllowOrigin, Cors, CorsHandler},
+    http::{
+        header::{
+            ACCEPT_ENCODING, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+        },
+        HeaderName, Method,
+    },
+};
+
+use crate::config::CorsSettings;
+use crate::error::{MindPulseError, MindPulseResult};
+
+/// 允许浏览器脚本读取的响应头
+const EXPOSE_HEADERS: [&str; 5] = ["etag", "deprecation", "link", "retry-after", "x-request-id"];
+
+/// 允许的来源
+#[derive(Debug, Clone)]
+enum OriginPattern {
+    /// `*`，允许任意来源
+    Any,
+    /// 完整匹配，如 `https://example.com`
+    Exact(String),
+    /// 通配子域名，如 `https://*.example.com`，不包含 `https://example.com` 本身
+    Subdomain { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> MindPulseResult<Self> {
+        let pattern = pattern.trim().trim_end_matches('/').to_ascii_lowercase();
+
+        if pattern == "*" {
+            return Ok(OriginPattern::Any);
+        }
+
+        let Some((scheme, host)) = pattern.split_once("://") else {
+            return Err(MindPulseError::Config(format!(
+                "CORS 来源必须包含协议：{}",
+                pattern
+            )));
+        };
+
+        match host.strip_prefix("*.") {
+            Some(domain) if !domain.is_empty() && !domain.contains('*') => {
+                Ok(OriginPattern::Subdomain {
+                    scheme: scheme.to_owned(),
+                    suffix: format!(".{}", domain),
+                })
+            }
+            None if !host.is_empty() && !host.contains('*') => Ok(OriginPattern::Exact(pattern)),
+            _ => Err(MindPulseError::Config(format!(
+                "无效的 CORS 来源：{}",
+                pattern
+            ))),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Any => true,
+            OriginPattern::Exact(exact) => origin.eq_ignore_ascii_case(exact),
+            OriginPattern::Subdomain { scheme, suffix } => {
+                let origin = origin.to_ascii_lowercase();
+                origin
+                    .strip_prefix(scheme.as_str())
+                    .and_then(|rest| rest.strip_prefix("://"))
+                    .is_some_and(|host| {
+                        host.len() > suffix.len() && host.ends_with(suffix.as_str())
+                    })
+            }
+        }
+    }
+}
+
+/// 跨域资源共享配置
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    origins: Vec<OriginPattern>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    max_age: u64,
+}
+
+impl CorsConfig {
+    /// 从配置中读取，`origins` 为空时不启用 CORS
+    pub fn from_settings(settings: &CorsSettings) -> MindPulseResult<Option<Self>> {
+        let origins = settings
+            .origins
+            .iter()
+            .map(|o| OriginPattern::parse(o))
+            .collect::<MindPulseResult<Vec<_>>>()?;
+        if origins.is_empty() {
+            return Ok(None);
+        }
+
+        let methods = settings
+            .methods
+            .iter()
+            .map(|m| {
+                Method::from_bytes(m.trim().to_ascii_uppercase().as_bytes())
+                    .map_err(|_| MindPulseError::Config(format!("无效的 CORS 请求方法：{}", m)))
+            })
+            .collect::<MindPulseResult<Vec<_>>>()?;
+
+        let headers = settings
+            .headers
+            .iter()
+            .map(|h| {
+                HeaderName::from_bytes(h.trim().as_bytes())
+                    .map_err(|_| MindPulseError::Config(format!("无效的 CORS 请求头：{}", h)))
+            })
+            .collect::<MindPulseResult<Vec<_>>>()?;
+
+        Ok(Some(Self {
+            origins,
+            methods,
+            headers,
+            max_age: settings.max_age,
+        }))
+    }
+
+    /// 生成 CORS 处理器
+    ///
+    /// 允许的来源按请求动态匹配，响应中回显具体的来源而不是 `*`。
+    pub fn into_handler(self) -> CorsHandler {
+        info!(
+            message = "CORS enabled",
+            origins = ?self.origins,
+            methods = ?self.methods,
+            max_age = self.max_age
+        );
+
+        let origins = self.origins;
+
+        Cors::new()
+            .allow_origin(AllowOrigin::dynamic(move |origin, _, _| {
+                let origin = origin?;
+                let value = origin.to_str().ok()?;
+                if origins.iter().any(|p| p.matches(value)) {
+                    Some(origin.clone())
+                } else {
+                    debug!(message = "CORS origin rejected", origin = value);
+                    None
+                }
+            }))
+            .allow_methods(self.methods)
+            .allow_headers(self.headers)
+            .expose_headers(EXPOSE_HEADERS.map(HeaderName::from_static))
+            .max_age(self.max_age)
+            // CORS 处理器会覆盖响应中已有的 Vary，这里补上压缩协商用到的 Accept-Encoding
+            .vary::<()>([
+                ORIGIN,
+                ACCESS_CONTROL_REQUEST_METHOD,
+                ACCESS_CONTROL_REQUEST_HEADERS,
+                ACCEPT_ENCODING,
+            ])
+            .into_handler()
+    }
+}
@@ -0,0 +1,41 @@
+use salvo::http::header::{HeaderValue, LINK};
+use salvo::http::{Request, Response};
+use salvo::{async_trait, Depot, FlowCtrl, Handler};
+
+/// 为旧版路由添加 `Deprecation` 响应头，并通过 `Link` 指向新版路由
+pub struct Deprecated {
+    /// 新版路由，`{id}` 会被替换为请求中的同名路径参数
+    successor: &'static str,
+}
+
+impl Deprecated {
+    pub fn new(successor: &'static str) -> Self {
+        Self { successor }
+    }
+}
+
+#[async_trait]
+impl Handler for Deprecated {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        ctrl.call_next(req, depot, res).await;
+
+        let successor = match req.param::<String>("id") {
+            Some(id) => self.successor.replace("{id}", &id),
+            None => self.successor.to_owned(),
+        };
+
+        res.headers_mut()
+            .insert("Deprecation", HeaderValue::from_static("true"));
+        if let Ok(link) =
+            HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))
+        {
+            res.headers_mut().insert(LINK, link);
+        }
+    }
+}
@@ -1,30 +1,338 @@
 use std::time::SystemTimeError;
 
-use salvo::{async_trait, http::StatusCode, writing::Text, Depot, Request, Response, Writer};
+use salvo::{
+    async_trait,
+    http::{
+        header::{ACCEPT_LANGUAGE, CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE},
+        HeaderValue, ParseError, ResBody, StatusCode,
+    },
+    oapi::{self, Components, EndpointOutRegister, Operation, ToSchema},
+    Depot, FlowCtrl, Handler, Request, Response, Writer,
+};
+use serde::Serialize;
+
+use crate::logger::REQUEST_ID;
 
 pub type MindPulseResult<T> = std::result::Result<T, MindPulseError>;
 
+const PROBLEM_JSON: &str = "application/problem+json";
+
 #[derive(Debug, thiserror::Error)]
 pub enum MindPulseError {
     #[error(transparent)]
     Sqlite(#[from] sqlx::Error),
     #[error(transparent)]
     SystemTime(#[from] SystemTimeError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
     #[error("无效的客户端类型：{0}")]
     InvalidClientType(u8),
+    #[error("答案无效：{0}")]
+    InvalidAnswers(String),
+    #[error("请求体校验失败：{message}")]
+    InvalidBody {
+        field: Option<String>,
+        message: String,
+    },
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("无效的量表 ID：{0}")]
+    ScaleNotFound(u16),
+    #[error("无效的量表标识：{0}")]
+    SlugNotFound(String),
+    #[error("请求过于频繁，请在 {0} 秒后重试")]
+    RateLimited(u64),
+    #[error("缺少或无效的访问令牌")]
+    Unauthorized,
+    #[error("服务暂时不可用：{0}")]
+    Unavailable(&'static str),
+    #[error("配置错误：{0}")]
+    Config(String),
     #[error("{0}")]
     Response(String),
 }
 
+impl MindPulseError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            MindPulseError::InvalidClientType(_)
+            | MindPulseError::InvalidAnswers(_)
+            | MindPulseError::InvalidBody { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            MindPulseError::Parse(ParseError::PayloadTooLarge) => StatusCode::PAYLOAD_TOO_LARGE,
+            MindPulseError::Parse(_) => StatusCode::BAD_REQUEST,
+            MindPulseError::ScaleNotFound(_) | MindPulseError::SlugNotFound(_) => {
+                StatusCode::NOT_FOUND
+            }
+            MindPulseError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            MindPulseError::Unauthorized => StatusCode::UNAUTHORIZED,
+            MindPulseError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            MindPulseError::Response(_) => StatusCode::BAD_REQUEST,
+            MindPulseError::Sqlite(_)
+            | MindPulseError::SystemTime(_)
+            | MindPulseError::Io(_)
+            | MindPulseError::Config(_)
+            | MindPulseError::Serialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 稳定的错误码，客户端应据此区分错误
+    fn code(&self) -> &'static str {
+        match self {
+            MindPulseError::Sqlite(_) => "DATABASE_ERROR",
+            MindPulseError::SystemTime(_)
+            | MindPulseError::Io(_)
+            | MindPulseError::Serialization(_) => "INTERNAL_ERROR",
+            MindPulseError::InvalidClientType(_) => "INVALID_CLIENT_TYPE",
+            MindPulseError::InvalidAnswers(_) => "INVALID_ANSWERS",
+            MindPulseError::InvalidBody { .. } | MindPulseError::Parse(_) => {
+                status_code(self.status_code())
+            }
+            MindPulseError::ScaleNotFound(_) | MindPulseError::SlugNotFound(_) => "SCALE_NOT_FOUND",
+            MindPulseError::RateLimited(_) => "RATE_LIMITED",
+            MindPulseError::Unauthorized => "UNAUTHORIZED",
+            MindPulseError::Unavailable(_) => "SERVICE_UNAVAILABLE",
+            MindPulseError::Config(_) => "CONFIG_ERROR",
+            MindPulseError::Response(_) => "BAD_REQUEST",
+        }
+    }
+
+    fn title(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (MindPulseError::InvalidClientType(_), Locale::Zh) => "无效的客户端类型",
+            (MindPulseError::InvalidClientType(_), Locale::En) => "Invalid client type",
+            (MindPulseError::InvalidAnswers(_), Locale::Zh) => "答案无效",
+            (MindPulseError::InvalidAnswers(_), Locale::En) => "Invalid answers",
+            (MindPulseError::ScaleNotFound(_) | MindPulseError::SlugNotFound(_), Locale::Zh) => {
+                "量表不存在"
+            }
+            (MindPulseError::ScaleNotFound(_) | MindPulseError::SlugNotFound(_), Locale::En) => {
+                "Scale not found"
+            }
+            _ => status_title(self.status_code(), locale),
+        }
+    }
+
+    /// 校验失败的字段
+    fn field(&self) -> Option<&str> {
+        match self {
+            MindPulseError::InvalidBody { field, .. } => field.as_deref(),
+            MindPulseError::InvalidClientType(_) => Some("client_type"),
+            MindPulseError::InvalidAnswers(_) => Some("answers"),
+            MindPulseError::ScaleNotFound(_) => Some("id"),
+            MindPulseError::SlugNotFound(_) => Some("slug"),
+            _ => None,
+        }
+    }
+}
+
+/// 错误信息的语言，取自 `Accept-Language`，默认中文
+#[derive(Debug, Clone, Copy)]
+enum Locale {
+    Zh,
+    En,
+}
+
+impl Locale {
+    fn from_request(req: &Request) -> Self {
+        let preferred = req
+            .headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .map(|v| v.trim().to_ascii_lowercase());
+
+        match preferred {
+            Some(lang) if lang.starts_with("en") => Locale::En,
+            _ => Locale::Zh,
+        }
+    }
+}
+
+fn status_title(status: StatusCode, locale: Locale) -> &'static str {
+    match (status, locale) {
+        (StatusCode::BAD_REQUEST, Locale::Zh) => "请求参数无效",
+        (StatusCode::UNAUTHORIZED, Locale::Zh) => "未授权",
+        (StatusCode::NOT_FOUND, Locale::Zh) => "资源不存在",
+        (StatusCode::METHOD_NOT_ALLOWED, Locale::Zh) => "不支持的请求方法",
+        (StatusCode::PAYLOAD_TOO_LARGE, Locale::Zh) => "请求体过大",
+        (StatusCode::UNSUPPORTED_MEDIA_TYPE, Locale::Zh) => "不支持的内容类型",
+        (StatusCode::UNPROCESSABLE_ENTITY, Locale::Zh) => "请求参数校验失败",
+        (StatusCode::TOO_MANY_REQUESTS, Locale::Zh) => "请求过于频繁",
+        (StatusCode::SERVICE_UNAVAILABLE, Locale::Zh) => "服务暂时不可用",
+        (status, Locale::Zh) if status.is_server_error() => "服务器内部错误",
+        (status, _) => status.canonical_reason().unwrap_or("Unknown Error"),
+    }
+}
+
+/// 框架产生的错误所对应的错误码
+fn status_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "BAD_REQUEST",
+        StatusCode::UNAUTHORIZED => "UNAUTHORIZED",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::METHOD_NOT_ALLOWED => "METHOD_NOT_ALLOWED",
+        StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "UNSUPPORTED_MEDIA_TYPE",
+        StatusCode::UNPROCESSABLE_ENTITY => "VALIDATION_FAILED",
+        StatusCode::TOO_MANY_REQUESTS => "RATE_LIMITED",
+        StatusCode::SERVICE_UNAVAILABLE => "SERVICE_UNAVAILABLE",
+        status if status.is_server_error() => "INTERNAL_ERROR",
+        _ => "CLIENT_ERROR",
+    }
+}
+
+/// RFC 7807 问题详情
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Problem {
+    /// 问题类型
+    #[serde(rename = "type")]
+    kind: String,
+    /// 本地化的简短描述
+    title: &'static str,
+    /// HTTP 状态码
+    status: u16,
+    /// 具体说明
+    detail: String,
+    /// 出错的请求路径
+    instance: String,
+    /// 稳定的错误码
+    code: &'static str,
+    /// 校验失败的字段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+    /// 请求 ID，用于在日志中定位
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl Problem {
+    fn new(req: &Request, status: StatusCode, code: &'static str, title: &'static str) -> Self {
+        Self {
+            kind: format!("urn:mind-pulse:error:{}", code.to_ascii_lowercase()),
+            title,
+            status: status.as_u16(),
+            detail: title.to_owned(),
+            instance: req.uri().path().to_owned(),
+            code,
+            field: None,
+            request_id: req
+                .headers()
+                .get(&REQUEST_ID)
+                .and_then(|v| v.to_str().ok())
+                .map(ToOwned::to_owned),
+        }
+    }
+
+    fn render(self, res: &mut Response) {
+        res.status_code(StatusCode::from_u16(self.status).unwrap_or_default());
+
+        match serde_json::to_vec(&self) {
+            Ok(body) => {
+                res.headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+                res.body(body);
+            }
+            Err(e) => {
+                error!(message = "Failed to serialize problem details", error = ?e);
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl Writer for MindPulseError {
-    async fn write(mut self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
-        match &self {
-            MindPulseError::Response(_) => res.status_code(StatusCode::BAD_REQUEST),
-            _ => res.status_code(StatusCode::INTERNAL_SERVER_ERROR),
+    async fn write(mut self, req: &mut Request, _depot: &mut Depot, res: &mut Response) {
+        let status = self.status_code();
+        let locale = Locale::from_request(req);
+
+        let mut problem = Problem::new(req, status, self.code(), self.title(locale));
+        problem.field = self.field().map(str::to_owned);
+
+        // 服务器内部错误不向客户端暴露细节
+        if status.is_server_error() {
+            error!(message = "Request failed", code = problem.code, error = ?self);
+        } else {
+            problem.detail = self.to_string();
+        }
+
+        match self {
+            MindPulseError::RateLimited(seconds) => {
+                res.headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(seconds));
+            }
+            MindPulseError::Unauthorized => {
+                res.headers_mut()
+                    .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            }
+            _ => {}
+        }
+
+        problem.render(res);
+    }
+}
+
+/// 将框架产生的错误（路由不存在、参数解析失败等）也渲染为问题详情
+pub struct ProblemCatcher;
+
+#[async_trait]
+impl Handler for ProblemCatcher {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let Some(status) = res.status_code else {
+            return;
         };
+        if !(status.is_client_error() || status.is_server_error()) {
+            return;
+        }
+
+        let locale = Locale::from_request(req);
+        let mut problem = Problem::new(
+            req,
+            status,
+            status_code(status),
+            status_title(status, locale),
+        );
+
+        // 参数解析失败等客户端错误可以说明原因
+        if let ResBody::Error(e) = &res.body {
+            if status.is_client_error() {
+                if let Some(cause) = &e.cause {
+                    problem.detail = cause.to_string();
+                } else if let Some(detail) = &e.detail {
+                    problem.detail = detail.clone();
+                }
+            }
+        }
+
+        problem.render(res);
+        ctrl.skip_rest();
+    }
+}
 
-        res.render(Text::Plain(self.to_string()));
+/// 登记所有可能的错误响应，各接口用 `status_codes(...)` 只保留自己会返回的状态码
+impl EndpointOutRegister for MindPulseError {
+    fn register(components: &mut Components, operation: &mut Operation) {
+        let schema = Problem::to_schema(components);
+        for (status, description) in [
+            (StatusCode::BAD_REQUEST, "请求参数无效"),
+            (StatusCode::NOT_FOUND, "量表不存在"),
+            (StatusCode::UNPROCESSABLE_ENTITY, "请求参数校验失败"),
+            (StatusCode::TOO_MANY_REQUESTS, "请求过于频繁"),
+            (StatusCode::INTERNAL_SERVER_ERROR, "服务器内部错误"),
+            (StatusCode::SERVICE_UNAVAILABLE, "服务暂时不可用"),
+        ] {
+            operation.responses.insert(
+                status.as_str(),
+                oapi::Response::new(description).add_content(PROBLEM_JSON, schema.clone()),
+            );
+        }
     }
 }
 
@@ -0,0 +1,134 @@
+use std::{cell::RefCell, ops::Deref};
+
+use salvo::{
+    extract::{Extractible, Metadata},
+    oapi::{self, endpoint::EndpointArgRegister, Components, Operation, ToSchema},
+    Depot, Request,
+};
+use serde::de::{value::MapDeserializer, DeserializeOwned};
+use serde_json::Value;
+
+use crate::error::MindPulseError;
+
+/// JSON 请求体，用法与 `salvo::oapi::extract::JsonBody` 相同
+///
+/// 语法错误仍返回 400；字段缺失或类型不符时返回 422，并在 `field` 中指明出错的字段。
+#[derive(Debug)]
+pub struct JsonBody<T>(pub T);
+
+impl<T> JsonBody<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for JsonBody<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'ex, T> Extractible<'ex> for JsonBody<T>
+where
+    T: DeserializeOwned + Send,
+{
+    fn metadata() -> &'static Metadata {
+        static METADATA: Metadata = Metadata::new("");
+        &METADATA
+    }
+
+    async fn extract(
+        req: &'ex mut Request,
+        _depot: &'ex mut Depot,
+    ) -> Result<Self, impl salvo::Writer + Send + std::fmt::Debug + 'static> {
+        let value: Value = req.parse_json().await.map_err(MindPulseError::from)?;
+        from_value(value).map(JsonBody)
+    }
+}
+
+impl<T> EndpointArgRegister for JsonBody<T>
+where
+    T: DeserializeOwned + ToSchema,
+{
+    fn register(components: &mut Components, operation: &mut Operation, arg: &str) {
+        oapi::extract::JsonBody::<T>::register(components, operation, arg);
+    }
+}
+
+/// 按字段反序列化，失败时记录最后读到的字段
+fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, MindPulseError> {
+    let Value::Object(map) = value else {
+        return T::deserialize(value).map_err(|e| MindPulseError::InvalidBody {
+            field: None,
+            message: e.to_string(),
+        });
+    };
+
+    let current = RefCell::new(None);
+    let entries = map
+        .into_iter()
+        .inspect(|(key, _)| *current.borrow_mut() = Some(key.clone()));
+
+    T::deserialize(MapDeserializer::<_, serde_json::Error>::new(entries)).map_err(|e| {
+        let message = e.to_string();
+        // 缺少字段时最后读到的字段并没有出错
+        let field = missing_field(&message)
+            .map(str::to_owned)
+            .or_else(|| current.take());
+
+        MindPulseError::InvalidBody { field, message }
+    })
+}
+
+/// 从 serde 的错误信息中取出缺少的字段名
+fn missing_field(message: &str) -> Option<&str> {
+    message
+        .strip_prefix("missing field `")?
+        .split_once('`')
+        .map(|(field, _)| field)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Body {
+        id: u16,
+        tags: Vec<String>,
+    }
+
+    fn invalid_field(value: Value) -> Option<String> {
+        match from_value::<Body>(value) {
+            Err(MindPulseError::InvalidBody { field, .. }) => field,
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_the_invalid_field() {
+        let body: Body = from_value(json!({ "id": 1, "tags": ["a"] })).unwrap();
+        assert_eq!((body.id, body.tags), (1, vec!["a".to_owned()]));
+
+        assert_eq!(invalid_field(json!({ "tags": [] })).as_deref(), Some("id"));
+        assert_eq!(invalid_field(json!({ "id": 1 })).as_deref(), Some("tags"));
+        assert_eq!(
+            invalid_field(json!({ "id": "1", "tags": [] })).as_deref(),
+            Some("id")
+        );
+        assert_eq!(
+            invalid_field(json!({ "id": 1, "tags": [1] })).as_deref(),
+            Some("tags")
+        );
+        assert_eq!(
+            invalid_field(json!({ "tags": [], "id": 70000 })).as_deref(),
+            Some("id")
+        );
+        assert_eq!(invalid_field(json!("body")), None);
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use salvo::{handler, http::StatusCode, writing::Json, Depot, Response};
+use serde::Serialize;
+
+use crate::scale::{loaded_scale_payloads, LIST};
+use crate::statistics::obtain_statistics;
+
+/// 数据库检查的超时时间，避免连接池耗尽时探针一直挂起
+const DATABASE_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// 就绪检查需要的状态，通过 `Depot` 注入
+#[derive(Debug)]
+pub struct Health {
+    /// SQLite 数据库所在目录，其他后端为空
+    data_dir: Option<PathBuf>,
+    /// 最小可用空间（字节）
+    min_free_disk: u64,
+}
+
+impl Health {
+    pub fn new(db_path: Option<PathBuf>, min_free_disk_mb: u64) -> Self {
+        let data_dir = db_path.map(|path| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        });
+
+        Self {
+            data_dir,
+            min_free_disk: min_free_disk_mb.saturating_mul(BYTES_PER_MB),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Ok,
+    Fail,
+    /// 当前配置下不适用，不影响就绪状态
+    Skipped,
+}
+
+/// 单项检查的结果
+#[derive(Debug, Serialize)]
+struct Check {
+    status: CheckStatus,
+    /// 检查耗时（毫秒）
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl Check {
+    fn new(started: Instant, result: Result<Option<String>, String>) -> Self {
+        // 保留到微秒
+        let latency_ms = started.elapsed().as_micros() as f64 / 1000.0;
+
+        match result {
+            Ok(detail) => Self {
+                status: CheckStatus::Ok,
+                latency_ms,
+                detail,
+            },
+            Err(detail) => Self {
+                status: CheckStatus::Fail,
+                latency_ms,
+                detail: Some(detail),
+            },
+        }
+    }
+
+    fn skipped(detail: &str) -> Self {
+        Self {
+            status: CheckStatus::Skipped,
+            latency_ms: 0.0,
+            detail: Some(detail.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Checks {
+    database: Check,
+    scales: Check,
+    disk: Check,
+}
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    /// `ready` 或 `not_ready`
+    status: &'static str,
+    checks: Checks,
+}
+
+#[derive(Debug, Serialize)]
+struct Liveness {
+    status: &'static str,
+}
+
+/// 存活检查，进程能处理请求即返回 200
+#[handler]
+pub async fn healthz(res: &mut Response) {
+    res.render(Json(Liveness { status: "ok" }));
+}
+
+/// 就绪检查，依次检查数据库、量表数据和磁盘空间，任意一项失败时返回 503
+#[handler]
+pub async fn readyz(depot: &mut Depot, res: &mut Response) {
+    let health = depot.obtain::<Arc<Health>>().ok();
+
+    let checks = Checks {
+        database: check_database(depot).await,
+        scales: check_scales(),
+        disk: match health {
+            Some(health) => check_disk(health),
+            None => Check::new(Instant::now(), Err("就绪检查状态缺失".to_owned())),
+        },
+    };
+
+    let ready = [&checks.database, &checks.scales, &checks.disk]
+        .iter()
+        .all(|c| c.status != CheckStatus::Fail);
+
+    if ready {
+        trace!(message = "Readiness check passed");
+    } else {
+        warn!(message = "Readiness check failed", checks = ?checks);
+        res.status_code(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    res.render(Json(Readiness {
+        status: if ready { "ready" } else { "not_ready" },
+        checks,
+    }));
+}
+
+async fn check_database(depot: &Depot) -> Check {
+    let started = Instant::now();
+
+    let result = match obtain_statistics(depot) {
+        Ok(statistics) => {
+            match tokio::time::timeout(DATABASE_CHECK_TIMEOUT, statistics.ping()).await {
+                Ok(Ok(())) => Ok(Some(statistics.backend().to_owned())),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err(format!(
+                    "超过 {} 秒未响应",
+                    DATABASE_CHECK_TIMEOUT.as_secs()
+                )),
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    };
+
+    Check::new(started, result)
+}
+
+fn check_scales() -> Check {
+    let started = Instant::now();
+
+    let result = match loaded_scale_payloads() {
+        Some(loaded) if loaded == LIST.len() => Ok(Some(format!("{} 个量表", loaded))),
+        Some(loaded) => Err(format!("只加载了 {}/{} 个量表", loaded, LIST.len())),
+        None => Err("量表数据尚未加载".to_owned()),
+    };
+
+    Check::new(started, result)
+}
+
+fn check_disk(health: &Health) -> Check {
+    let Some(dir) = &health.data_dir else {
+        return Check::skipped("未使用 SQLite");
+    };
+
+    let started = Instant::now();
+    let result = available_space(dir).and_then(|available| {
+        let detail = format!("可用 {} MB", available / BYTES_PER_MB);
+        if available >= health.min_free_disk {
+            Ok(Some(detail))
+        } else {
+            Err(format!(
+                "{}，低于 {} MB",
+                detail,
+                health.min_free_disk / BYTES_PER_MB
+            ))
+        }
+    });
+
+    Check::new(started, result)
+}
+
+fn available_space(dir: &Path) -> Result<u64, String> {
+    fs4::available_space(dir).map_err(|e| format!("无法读取 {} 的可用空间：{}", dir.display(), e))
+}
@@ -0,0 +1,505 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use time::{macros::format_description, OffsetDateTime, Time, UtcOffset};
+
+use crate::config::{LogConfig, LogRotation};
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// 压缩后的文件后缀
+const GZIP_SUFFIX: &str = ".gz";
+
+/// 按时间和大小轮换的日志文件
+///
+/// 当前写入的文件名为 `{file_name}.{日期}`（不按时间轮换时为 `{file_name}`），
+/// 超过大小限制时重命名为 `{file_name}.{日期}.{序号}`。轮换后在后台压缩旧文件，
+/// 并删除超出数量或天数限制的文件。
+pub struct RollingFile {
+    dir: PathBuf,
+    file_name: String,
+    rotation: LogRotation,
+    max_size: Option<u64>,
+    retention: Arc<Retention>,
+    utc_offset: UtcOffset,
+    /// 当前时间段，如 `2024-01-01`，不按时间轮换时为空
+    period: Option<String>,
+    /// 下一次按时间轮换的时刻
+    next_rollover: Option<OffsetDateTime>,
+    file: File,
+    size: u64,
+}
+
+/// 已轮换文件的清理策略
+struct Retention {
+    dir: PathBuf,
+    file_name: String,
+    max_files: usize,
+    max_age: Option<Duration>,
+    compress: bool,
+    /// 同一时间只运行一次清理
+    lock: Mutex<()>,
+}
+
+impl RollingFile {
+    pub fn new(config: &LogConfig, utc_offset: UtcOffset) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+
+        let (period, next_rollover) = period(config.rotation, now(utc_offset))?;
+
+        let retention = Arc::new(Retention {
+            dir: config.dir.clone(),
+            file_name: config.file_name.clone(),
+            max_files: config.max_files,
+            max_age: (config.max_age_days > 0)
+                .then(|| Duration::from_secs(config.max_age_days * SECONDS_PER_DAY)),
+            compress: config.compress,
+            lock: Mutex::new(()),
+        });
+
+        let (file, size) = open(&active_path(
+            &config.dir,
+            &config.file_name,
+            period.as_deref(),
+        ))?;
+
+        let this = Self {
+            dir: config.dir.clone(),
+            file_name: config.file_name.clone(),
+            rotation: config.rotation,
+            max_size: (config.max_size_mb > 0)
+                .then(|| config.max_size_mb.saturating_mul(BYTES_PER_MB)),
+            retention,
+            utc_offset,
+            period,
+            next_rollover,
+            file,
+            size,
+        };
+
+        // 处理上次运行留下的文件
+        this.cleanup();
+
+        Ok(this)
+    }
+
+    fn active_path(&self) -> PathBuf {
+        active_path(&self.dir, &self.file_name, self.period.as_deref())
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        (self.file, self.size) = open(&self.active_path())?;
+        Ok(())
+    }
+
+    /// 进入新的时间段，上一时间段的文件保持原名
+    fn roll_period(&mut self, now: OffsetDateTime) -> io::Result<()> {
+        self.file.flush()?;
+
+        (self.period, self.next_rollover) = period(self.rotation, now)?;
+        self.reopen()?;
+        self.cleanup();
+
+        Ok(())
+    }
+
+    /// 当前文件超过大小限制，重命名为带序号的文件后重新打开
+    fn roll_size(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let active = self.active_path();
+        let mut rotated = active.clone().into_os_string();
+        rotated.push(format!(".{}", self.next_index(&active)));
+        fs::rename(&active, &rotated)?;
+
+        self.reopen()?;
+        self.cleanup();
+
+        Ok(())
+    }
+
+    /// 当前时间段内下一个未使用的序号，从 1 开始
+    fn next_index(&self, active: &Path) -> u32 {
+        let Some(prefix) = active.file_name().and_then(|n| n.to_str()) else {
+            return 1;
+        };
+        let prefix = format!("{}.", prefix);
+
+        list(&self.dir)
+            .iter()
+            .filter_map(|name| name.to_str()?.strip_prefix(&prefix))
+            .filter_map(|rest| {
+                rest.strip_suffix(GZIP_SUFFIX)
+                    .unwrap_or(rest)
+                    .parse::<u32>()
+                    .ok()
+            })
+            .max()
+            .unwrap_or(0)
+            + 1
+    }
+
+    /// 在后台压缩并清理已轮换的文件，不阻塞日志写入
+    fn cleanup(&self) {
+        let retention = self.retention.clone();
+        let active = self.active_path();
+
+        std::thread::spawn(move || retention.run(&active));
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(next) = self.next_rollover {
+            let now = now(self.utc_offset);
+            if now >= next {
+                self.roll_period(now)?;
+            }
+        }
+
+        if let Some(max) = self.max_size {
+            if self.size > 0 && self.size + buf.len() as u64 > max {
+                self.roll_size()?;
+            }
+        }
+
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Retention {
+    fn run(&self, active: &Path) {
+        let Ok(_guard) = self.lock.lock() else {
+            return;
+        };
+
+        if self.compress {
+            for path in self.rotated_files(active) {
+                if path.extension().is_some_and(|ext| ext == "gz") {
+                    continue;
+                }
+                if let Err(e) = compress(&path) {
+                    warn!(message = "Failed to compress log file", path = %path.display(), error = ?e);
+                }
+            }
+        }
+
+        let mut files: Vec<(PathBuf, SystemTime)> = self
+            .rotated_files(active)
+            .into_iter()
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect();
+        // 最新的在前
+        files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+
+        let now = SystemTime::now();
+        for (index, (path, modified)) in files.iter().enumerate() {
+            let too_many = self.max_files > 0 && index >= self.max_files;
+            let too_old = self.max_age.is_some_and(|max_age| {
+                now.duration_since(*modified).is_ok_and(|age| age > max_age)
+            });
+
+            if too_many || too_old {
+                match fs::remove_file(path) {
+                    Ok(()) => debug!(message = "Old log file removed", path = %path.display()),
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => {
+                        warn!(message = "Failed to remove old log file", path = %path.display(), error = ?e)
+                    }
+                }
+            }
+        }
+    }
+
+    /// 除当前写入的文件外，所有属于该日志的文件
+    fn rotated_files(&self, active: &Path) -> Vec<PathBuf> {
+        let prefix = format!("{}.", self.file_name);
+
+        list(&self.dir)
+            .into_iter()
+            .filter(|name| name.to_str().is_some_and(|name| name.starts_with(&prefix)))
+            .map(|name| self.dir.join(name))
+            .filter(|path| path != active)
+            .collect()
+    }
+}
+
+fn active_path(dir: &Path, file_name: &str, period: Option<&str>) -> PathBuf {
+    match period {
+        Some(period) => dir.join(format!("{}.{}", file_name, period)),
+        None => dir.join(file_name),
+    }
+}
+
+/// 以追加方式打开，返回文件及其当前大小
+fn open(path: &Path) -> io::Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn now(utc_offset: UtcOffset) -> OffsetDateTime {
+    OffsetDateTime::now_utc().to_offset(utc_offset)
+}
+
+/// 时间段名称和下一次轮换的时刻
+fn period(
+    rotation: LogRotation,
+    now: OffsetDateTime,
+) -> io::Result<(Option<String>, Option<OffsetDateTime>)> {
+    let (name, start, length) = match rotation {
+        LogRotation::Never => return Ok((None, None)),
+        LogRotation::Daily => (
+            now.format(format_description!("[year]-[month]-[day]")),
+            now.replace_time(Time::MIDNIGHT),
+            time::Duration::DAY,
+        ),
+        LogRotation::Hourly => (
+            now.format(format_description!("[year]-[month]-[day]-[hour]")),
+            now.replace_time(Time::MIDNIGHT) + time::Duration::hours(now.hour().into()),
+            time::Duration::HOUR,
+        ),
+    };
+
+    let name = name.map_err(io::Error::other)?;
+    Ok((Some(name), Some(start + length)))
+}
+
+fn list(dir: &Path) -> Vec<OsString> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 压缩为同名的 `.gz` 文件后删除原文件
+fn compress(path: &Path) -> io::Result<()> {
+    let mut target = path.as_os_str().to_owned();
+    target.push(GZIP_SUFFIX);
+    let target = PathBuf::from(target);
+
+    let input = File::open(path)?;
+    let modified = input.metadata()?.modified()?;
+
+    let mut encoder = GzEncoder::new(
+        BufWriter::new(File::create(&target)?),
+        Compression::default(),
+    );
+    io::copy(&mut BufReader::new(input), &mut encoder)?;
+    let output = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+    // 保留原文件的修改时间，按天数清理时不受压缩影响
+    output.set_modified(modified)?;
+
+    fs::remove_file(path)?;
+    debug!(message = "Log file compressed", path = %target.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use tempfile::TempDir;
+    use time::macros::datetime;
+
+    use super::*;
+
+    const FILE_NAME: &str = "app.log";
+
+    fn config(dir: &TempDir, rotation: LogRotation) -> LogConfig {
+        LogConfig {
+            dir: dir.path().to_owned(),
+            file_name: FILE_NAME.to_owned(),
+            rotation,
+            max_files: 0,
+            ..Default::default()
+        }
+    }
+
+    fn retention(dir: &TempDir, max_files: usize, max_age: Option<Duration>) -> Retention {
+        Retention {
+            dir: dir.path().to_owned(),
+            file_name: FILE_NAME.to_owned(),
+            max_files,
+            max_age,
+            compress: false,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 创建文件并设置修改时间为 `age` 之前
+    fn touch(dir: &TempDir, name: &str, age: Duration) -> PathBuf {
+        let path = dir.path().join(name);
+        let file = File::create(&path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+        path
+    }
+
+    fn names(dir: &TempDir) -> Vec<String> {
+        let mut names: Vec<_> = list(dir.path())
+            .into_iter()
+            .map(|name| name.into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn period_names_and_rollover() {
+        let now = datetime!(2024-01-31 13:45 +8);
+
+        assert_eq!(period(LogRotation::Never, now).unwrap(), (None, None));
+        assert_eq!(
+            period(LogRotation::Daily, now).unwrap(),
+            (
+                Some("2024-01-31".to_owned()),
+                Some(datetime!(2024-02-01 00:00 +8))
+            )
+        );
+        assert_eq!(
+            period(LogRotation::Hourly, now).unwrap(),
+            (
+                Some("2024-01-31-13".to_owned()),
+                Some(datetime!(2024-01-31 14:00 +8))
+            )
+        );
+    }
+
+    #[test]
+    fn rolls_into_new_period() {
+        let dir = TempDir::new().unwrap();
+        let mut file = RollingFile::new(&config(&dir, LogRotation::Daily), UtcOffset::UTC).unwrap();
+        let current = file.active_path();
+
+        // 模拟上一时间段写入的文件
+        file.period = Some("2000-01-01".to_owned());
+        file.reopen().unwrap();
+        file.write_all(b"old\n").unwrap();
+        file.next_rollover = Some(datetime!(2000-01-02 00:00 UTC));
+
+        file.write_all(b"new\n").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(file.active_path(), current);
+        assert!(file.next_rollover.unwrap() > now(UtcOffset::UTC));
+        let old = dir.path().join(format!("{}.2000-01-01", FILE_NAME));
+        assert_eq!(fs::read_to_string(old).unwrap(), "old\n");
+        assert_eq!(fs::read_to_string(current).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn rolls_by_size() {
+        let dir = TempDir::new().unwrap();
+        let mut file = RollingFile::new(&config(&dir, LogRotation::Never), UtcOffset::UTC).unwrap();
+        file.max_size = Some(8);
+
+        for line in ["first\n", "second\n", "third\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let path = |suffix: &str| dir.path().join(format!("{}{}", FILE_NAME, suffix));
+        assert_eq!(fs::read_to_string(path(".1")).unwrap(), "first\n");
+        assert_eq!(fs::read_to_string(path(".2")).unwrap(), "second\n");
+        assert_eq!(fs::read_to_string(path("")).unwrap(), "third\n");
+    }
+
+    #[test]
+    fn next_index_skips_used_numbers() {
+        let dir = TempDir::new().unwrap();
+        let file = RollingFile::new(&config(&dir, LogRotation::Never), UtcOffset::UTC).unwrap();
+        let active = file.active_path();
+        assert_eq!(file.next_index(&active), 1);
+
+        for name in [
+            "app.log.1",
+            "app.log.3.gz",
+            "app.log.x",
+            "app.log.2024-01-01.7",
+            "other.log.9",
+        ] {
+            touch(&dir, name, Duration::ZERO);
+        }
+        assert_eq!(file.next_index(&active), 4);
+    }
+
+    #[test]
+    fn retention_keeps_newest_files() {
+        let dir = TempDir::new().unwrap();
+        let active = touch(&dir, FILE_NAME, Duration::from_secs(10 * SECONDS_PER_DAY));
+        for day in 1..=4 {
+            touch(
+                &dir,
+                &format!("{}.2024-01-0{}", FILE_NAME, day),
+                Duration::from_secs((5 - day) * SECONDS_PER_DAY),
+            );
+        }
+        touch(&dir, "other.log", Duration::from_secs(10 * SECONDS_PER_DAY));
+
+        retention(&dir, 2, None).run(&active);
+
+        assert_eq!(
+            names(&dir),
+            [
+                "app.log",
+                "app.log.2024-01-03",
+                "app.log.2024-01-04",
+                "other.log"
+            ]
+        );
+    }
+
+    #[test]
+    fn retention_removes_old_files() {
+        let dir = TempDir::new().unwrap();
+        let active = touch(&dir, FILE_NAME, Duration::from_secs(10 * SECONDS_PER_DAY));
+        touch(&dir, "app.log.1", Duration::from_secs(SECONDS_PER_DAY / 2));
+        touch(&dir, "app.log.2", Duration::from_secs(2 * SECONDS_PER_DAY));
+
+        retention(&dir, 0, Some(Duration::from_secs(SECONDS_PER_DAY))).run(&active);
+
+        assert_eq!(names(&dir), ["app.log", "app.log.1"]);
+    }
+
+    #[test]
+    fn retention_compresses_rotated_files() {
+        let dir = TempDir::new().unwrap();
+        let active = touch(&dir, FILE_NAME, Duration::ZERO);
+        let rotated = dir.path().join("app.log.1");
+        fs::write(&rotated, "rotated\n").unwrap();
+
+        let mut retention = retention(&dir, 0, None);
+        retention.compress = true;
+        retention.run(&active);
+
+        assert_eq!(names(&dir), ["app.log", "app.log.1.gz"]);
+        let mut content = String::new();
+        GzDecoder::new(File::open(dir.path().join("app.log.1.gz")).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "rotated\n");
+    }
+}
@@ -1,10 +1,62 @@
 use std::time::Instant;
 
-use salvo::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
-use salvo::http::{Request, Response, StatusCode};
+use salvo::http::header::{HeaderName, CONTENT_LENGTH, CONTENT_TYPE};
+use salvo::http::{HeaderValue, Request, Response, StatusCode};
 use salvo::{async_trait, Depot, FlowCtrl, Handler};
-use tracing::info;
+use tracing::{info, info_span, warn, Instrument, Span};
+use ulid::Ulid;
 
+use crate::metrics::{metrics, route_template};
+use crate::rate_limit::RateLimited;
+
+/// 请求 ID 的请求头和响应头
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 客户端传入的请求 ID 的最大长度
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 使用客户端或反向代理传入的请求 ID，没有或格式不合法时生成新的 ULID
+fn request_id(req: &Request) -> HeaderValue {
+    req.headers()
+        .get(&REQUEST_ID)
+        .filter(|v| {
+            let v = v.as_bytes();
+            !v.is_empty()
+                && v.len() <= MAX_REQUEST_ID_LEN
+                && v.iter()
+                    .all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(b))
+        })
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Ulid::new().to_string()).expect("ULID 只包含 ASCII 字符")
+        })
+}
+
+#[cfg(not(feature = "otel"))]
+fn request_span(_req: &Request, id: &HeaderValue) -> Span {
+    info_span!("request", request_id = id.to_str().unwrap_or_default())
+}
+
+/// 导出链路时附加 HTTP 语义约定的字段，路由和状态码在请求结束后记录
+#[cfg(feature = "otel")]
+fn request_span(req: &Request, id: &HeaderValue) -> Span {
+    use tracing::field::Empty;
+
+    let span = info_span!(
+        "request",
+        request_id = id.to_str().unwrap_or_default(),
+        otel.name = Empty,
+        otel.kind = "server",
+        otel.status_code = Empty,
+        http.request.method = req.method().as_str(),
+        http.route = Empty,
+        http.response.status_code = Empty,
+    );
+    crate::telemetry::set_remote_parent(&span, req.headers());
+    span
+}
+
+/// 记录每个请求，并为请求内的所有日志加上请求 ID
 pub struct Logger;
 
 #[async_trait]
@@ -16,12 +68,34 @@ impl Handler for Logger {
         res: &mut Response,
         ctrl: &mut FlowCtrl,
     ) {
+        let id = request_id(req);
+        // 写回请求头，出错时问题详情从这里读取
+        req.headers_mut().insert(REQUEST_ID, id.clone());
+
+        let span = request_span(req, &id);
+
         async move {
             let now = Instant::now();
             ctrl.call_next(req, depot, res).await;
             let duration = now.elapsed();
 
+            res.headers_mut().insert(REQUEST_ID, id);
+
             let status = res.status_code.unwrap_or(StatusCode::OK);
+            let route = route_template(depot);
+
+            metrics().observe_request(route, req.method().as_str(), status.as_u16(), duration);
+
+            #[cfg(feature = "otel")]
+            {
+                let span = Span::current();
+                span.record("otel.name", format!("{} {}", req.method(), route));
+                span.record("http.route", route);
+                span.record("http.response.status_code", status.as_u16());
+                if status.is_server_error() {
+                    span.record("otel.status_code", "ERROR");
+                }
+            }
 
             let headers = req.headers();
 
@@ -36,7 +110,17 @@ impl Handler for Logger {
                 client = ?headers.get("X-Forwarded-For"),
                 queries = ?req.queries()
             );
+
+            if let Ok(limited) = depot.obtain::<RateLimited>() {
+                warn!(
+                    message = "Client rate limited",
+                    route = limited.route,
+                    client = limited.client,
+                    path = req.uri().path()
+                );
+            }
         }
+        .instrument(span)
         .await
     }
 }
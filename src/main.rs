@@ -1,22 +1,60 @@
+mod backup;
+mod client;
+mod command;
+mod config;
+mod cors;
+mod deprecation;
 mod error;
+mod extract;
+mod health;
+mod log_file;
 mod logger;
+mod metrics;
+mod rate_limit;
+mod routes;
 mod scale;
+#[cfg(unix)]
+mod socket;
 mod statistics;
+#[cfg(feature = "otel")]
+mod telemetry;
+#[cfg(feature = "tls")]
+mod tls;
 
 #[macro_use]
 extern crate tracing;
 
-use salvo::oapi::extract::PathParam;
+use std::{process::ExitCode, sync::Arc, time::Duration};
+
+use clap::Parser;
+use salvo::catcher::Catcher;
+use salvo::conn::Acceptor;
+use salvo::cors::CorsHandler;
 use salvo::prelude::*;
 use salvo::writing::Json;
-use time::macros::{format_description, offset};
-use tracing::Level;
+use salvo_compression::{Compression, CompressionLevel};
+use time::macros::format_description;
 use tracing_subscriber::fmt::time::OffsetTime;
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
-use crate::error::MindPulseResult;
+use crate::backup::Backups;
+use crate::client::TrustedProxies;
+use crate::command::Command;
+use crate::config::{Cli, Config, LogFormat};
+use crate::cors::CorsConfig;
+use crate::error::{MindPulseError, MindPulseResult, ProblemCatcher};
+use crate::health::Health;
+use crate::log_file::RollingFile;
 use crate::logger::Logger;
-use crate::scale::{get_scale_json_by_id, LIST};
-use crate::statistics::{create_statistics_table, handle_get_statistics, handle_insert_record};
+use crate::rate_limit::RateLimitStore;
+use crate::routes::{create_router, RATE_LIMITED_ROUTES};
+use crate::scale::init_scale_payloads;
+use crate::statistics::{connect_statistics_store, AccessMode, Statistics};
+#[cfg(feature = "otel")]
+use crate::telemetry::Telemetry;
 
 trait JsonRender {
     fn json<S>(&mut self, data: S)
@@ -33,50 +71,243 @@ impl JsonRender for Response {
     }
 }
 
-#[handler]
-async fn list(res: &mut Response) {
-    res.json(LIST);
+/// 动态响应超过该大小（字节）时才压缩
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// 等待 SIGINT 或 SIGTERM，返回信号名称
+async fn shutdown_signal() -> &'static str {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(message = "Failed to listen for SIGINT", error = ?e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!(message = "Failed to listen for SIGTERM", error = ?e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => "SIGINT",
+        _ = terminate => "SIGTERM",
+    }
+}
+
+/// 运行服务直到收到关闭信号
+///
+/// 第一次信号停止接受新连接，等待进行中的请求完成，超时后强制关闭；再次收到信号时立即关闭。
+async fn run<A>(acceptor: A, service: Service, shutdown_timeout: Duration)
+where
+    A: Acceptor + Send + 'static,
+{
+    let server = Server::new(acceptor);
+
+    let handle = server.handle();
+    tokio::spawn(async move {
+        let signal = shutdown_signal().await;
+        info!(
+            message = "Received shutdown signal, draining connections",
+            signal,
+            timeout = ?shutdown_timeout
+        );
+        handle.stop_graceful(shutdown_timeout);
+
+        // 再次收到信号时不再等待
+        let signal = shutdown_signal().await;
+        warn!(
+            message = "Received second shutdown signal, stopping immediately",
+            signal
+        );
+        handle.stop_forcible();
+    });
+
+    server.serve(service).await;
 }
 
-#[handler]
-async fn item(id: PathParam<u16>, res: &mut Response) -> MindPulseResult<()> {
-    let value = get_scale_json_by_id(*id)?;
-    res.json(value);
+async fn serve(
+    config: &Config,
+    statistics: Arc<Statistics>,
+    rate_limits: Arc<RateLimitStore>,
+    health: Arc<Health>,
+    backups: Arc<Backups>,
+    cors: Option<CorsHandler>,
+) -> MindPulseResult<()> {
+    let router = create_router(&config.rate_limit, config.admin.token.is_some());
+
+    let mut service = Service::new(router).hoop(Logger);
+    // 放在路由之前，预检请求即使没有匹配的 OPTIONS 路由也能得到响应
+    if let Some(cors) = cors {
+        service = service.hoop(cors);
+    }
+
+    let service = service
+        .hoop(
+            // 量表数据已在启动时压缩，这里只处理统计等动态响应
+            Compression::new()
+                .disable_all()
+                .enable_brotli(CompressionLevel::Fastest)
+                .enable_gzip(CompressionLevel::Default)
+                .min_length(COMPRESSION_THRESHOLD),
+        )
+        .hoop(
+            affix_state::inject(statistics)
+                .inject(rate_limits)
+                .inject(health)
+                .inject(backups)
+                .inject(Arc::new(TrustedProxies::new(
+                    config.server.trusted_proxies.clone(),
+                ))),
+        )
+        .catcher(Catcher::default().hoop(ProblemCatcher));
+
+    let shutdown_timeout = config.server.shutdown_timeout();
+
+    #[cfg(unix)]
+    if let Some(path) = &config.server.socket {
+        let acceptor = socket::bind(path, config.server.socket_mode).await?;
+
+        info!(
+            "Server running on unix:{} (mode {})",
+            path.display(),
+            config.server.socket_mode
+        );
+        run(acceptor, service, shutdown_timeout).await;
+        socket::remove(path);
+
+        info!(message = "Server stopped");
+        return Ok(());
+    }
+
+    let address = config.server.address();
+    let listener = TcpListener::new(address);
+
+    #[cfg(feature = "tls")]
+    if let Some((cert, key)) = config.tls.files() {
+        let acceptor = listener
+            .rustls(tls::config_stream(cert, key)?)
+            .try_bind()
+            .await
+            .map_err(|e| bind_error(address, e))?;
+
+        let redirect = match config.tls.redirect_port {
+            Some(port) => {
+                let redirect_address = std::net::SocketAddr::new(config.server.bind, port);
+                let acceptor = tls::redirect_acceptor(redirect_address).await?;
+                info!("Redirecting http://{} to HTTPS", redirect_address);
+                Some(tokio::spawn(run(
+                    acceptor,
+                    tls::redirect_service(address.port()),
+                    shutdown_timeout,
+                )))
+            }
+            None => None,
+        };
+
+        info!("Server running on https://{}", address);
+        run(acceptor, service, shutdown_timeout).await;
 
+        if let Some(redirect) = redirect {
+            let _ = redirect.await;
+        }
+
+        info!(message = "Server stopped");
+        return Ok(());
+    }
+
+    let acceptor = listener
+        .try_bind()
+        .await
+        .map_err(|e| bind_error(address, e))?;
+
+    info!("Server running on {}", address);
+    run(acceptor, service, shutdown_timeout).await;
+
+    info!(message = "Server stopped");
     Ok(())
 }
 
-#[handler]
-async fn version(res: &mut Response) {
-    let v = env!("CARGO_PKG_VERSION");
-    res.render(v);
+fn bind_error(address: impl std::fmt::Display, e: salvo::Error) -> MindPulseError {
+    error!(message = "Failed to bind listener", address = %address, error = ?e);
+    MindPulseError::Config(format!("无法监听 {}：{}", address, e))
 }
 
-async fn serve(port: u16) {
-    let router = Router::new()
-        .push(Router::with_path("version").get(version))
-        .push(Router::with_path("list").get(list))
-        .push(
-            Router::with_path("scales")
-                .get(list)
-                .push(Router::with_path("{id}").get(item)),
+/// 启动 HTTP 服务，关闭后保存限流状态并写入剩余的完成记录
+async fn run_server(config: &Config) -> MindPulseResult<()> {
+    let cors = CorsConfig::from_settings(&config.cors)?.map(CorsConfig::into_handler);
+
+    init_scale_payloads()?;
+
+    let store = connect_statistics_store(&config.database, AccessMode::ReadWrite).await?;
+    let statistics = Statistics::init(store, config.utc_offset.0).await?;
+    #[cfg(feature = "otel")]
+    telemetry::observe_statistics(&statistics);
+
+    let rate_limits = RateLimitStore::init(RATE_LIMITED_ROUTES, config.rate_limit.state.clone());
+    let health = Arc::new(Health::new(
+        config.database.sqlite_path(),
+        config.health.min_free_disk_mb,
+    ));
+
+    let backups = Arc::new(Backups::new(
+        &config.backup,
+        &config.admin,
+        config.utc_offset.0,
+    ));
+    let scheduled_backups = config.backup.interval_hours.map(|hours| {
+        backups.clone().schedule(
+            statistics.store().clone(),
+            Duration::from_secs(hours * 3600),
         )
-        .push(Router::with_path("statistics").get(handle_insert_record))
-        .push(Router::with_path("get_statistics").get(handle_get_statistics));
+    });
 
-    let service = Service::new(router).hoop(Logger);
+    let served = serve(
+        config,
+        statistics.clone(),
+        rate_limits.clone(),
+        health,
+        backups,
+        cors,
+    )
+    .await;
 
-    let address = format!("127.0.0.1:{}", port);
-    info!("Server running on {}", address);
+    if let Some(scheduled_backups) = scheduled_backups {
+        scheduled_backups.abort();
+    }
+
+    rate_limits.persist();
+
+    // 确保队列中尚未写入的完成记录落盘，然后关闭连接池
+    statistics.shutdown(config.server.shutdown_timeout()).await;
 
-    let listener = TcpListener::new(address).bind().await;
-    Server::new(listener).serve(service).await;
+    served
 }
 
 #[tokio::main]
-async fn main() -> MindPulseResult<()> {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // 日志尚未初始化，配置错误直接输出到标准错误
+    let config = Config::load(&cli).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
     let timer = OffsetTime::new(
-        offset!(+8),
+        config.utc_offset.0,
         if cfg!(debug_assertions) {
             format_description!("[hour]:[minute]:[second].[subsecond digits:3]")
         } else {
@@ -86,48 +317,86 @@ async fn main() -> MindPulseResult<()> {
         },
     );
 
-    // NOTE: _guard must be a top-level variable
-    let (writer, _guard) = {
-        let file_appender = tracing_appender::rolling::daily("./log", "confidant-server.log");
-        tracing_appender::non_blocking(file_appender)
-    };
+    let file = RollingFile::new(&config.log, config.utc_offset.0).unwrap_or_else(|e| {
+        eprintln!("无法打开日志目录 {}：{}", config.log.dir.display(), e);
+        std::process::exit(1);
+    });
 
-    #[cfg(debug_assertions)]
-    let writer = {
-        use tracing_subscriber::fmt::writer::MakeWriterExt;
-        std::io::stderr.and(writer)
-    };
+    // NOTE: log_guard must be a top-level variable, dropping it flushes buffered lines
+    let (writer, log_guard) = tracing_appender::non_blocking(file);
 
-    let log_level = if cfg!(debug_assertions) {
-        Level::TRACE
+    let writer = if config.log.stderr {
+        BoxMakeWriter::new(std::io::stderr.and(writer))
     } else {
-        Level::WARN
+        BoxMakeWriter::new(writer)
     };
 
-    let builder = tracing_subscriber::fmt()
-        .with_max_level(log_level)
+    let fmt_layer = tracing_subscriber::fmt::layer()
         .with_file(true)
         .with_line_number(true)
         .with_target(false)
-        .with_env_filter("server")
         .with_timer(timer)
         .with_writer(writer);
 
-    #[cfg(debug_assertions)]
-    builder.init();
+    let fmt_layer = match config.log.format {
+        LogFormat::Pretty => fmt_layer.boxed(),
+        LogFormat::Json => fmt_layer.json().boxed(),
+    };
+
+    // 过滤指令已在加载配置时校验，日志和链路导出各自使用自己的过滤规则
+    let registry = tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(EnvFilter::new(&config.log.filter)));
+
+    #[cfg(feature = "otel")]
+    let (telemetry, registry) = match Telemetry::init(&config.otel) {
+        Ok(Some((telemetry, layer))) => (Some(telemetry), registry.with(Some(layer))),
+        Ok(None) => (None, registry.with(None)),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
 
-    #[cfg(not(debug_assertions))]
-    builder.json().init();
+    registry.init();
 
-    create_statistics_table().await?;
+    #[cfg(feature = "otel")]
+    if let Some(endpoint) = telemetry.as_ref().and(config.otel.endpoint.as_deref()) {
+        info!(
+            message = "Trace and metric export enabled",
+            endpoint,
+            protocol = ?config.otel.protocol,
+            sample_ratio = config.otel.sample_ratio,
+            metric_interval_secs = config.otel.metric_interval_secs
+        );
+    }
 
-    // 解析命令行参数获取端口号，默认为 4819
-    let port = std::env::args()
-        .nth(1)
-        .and_then(|arg| arg.parse::<u16>().ok())
-        .unwrap_or(4819);
+    let result = match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => run_server(&config).await.map(|_| ExitCode::SUCCESS),
+        Command::Migrate => command::migrate(&config).await,
+        Command::ValidateScales => Ok(command::validate()),
+        Command::ExportStatistics { format, output } => {
+            command::export_statistics(&config, format, output).await
+        }
+        Command::Stats { id } => command::stats(&config, id).await,
+        Command::Backup { output } => command::backup(&config, output).await,
+        Command::Restore { file } => command::restore(&config, file).await,
+        Command::DumpScale { id, pretty } => command::dump_scale(id, pretty),
+    };
 
-    serve(port).await;
+    let code = result.unwrap_or_else(|e| {
+        error!(message = "Command failed", error = ?e);
+        eprintln!("{}", e);
+        ExitCode::FAILURE
+    });
 
-    Ok(())
+    // 发送尚未导出的 span 和指标
+    #[cfg(feature = "otel")]
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown().await;
+    }
+
+    // 退出前写出非阻塞写入器中缓冲的日志
+    drop(log_guard);
+
+    code
 }
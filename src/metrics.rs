@@ -0,0 +1,269 @@
+use std::{sync::LazyLock, time::Duration};
+
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use salvo::{
+    async_trait, handler,
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    Depot, FlowCtrl, Handler, Request, Response,
+};
+
+use crate::error::{MindPulseError, MindPulseResult};
+use crate::statistics::obtain_statistics;
+#[cfg(feature = "otel")]
+use crate::telemetry::otel_metrics;
+
+/// Prometheus 文本格式
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 没有匹配任何路由的请求使用的标签，避免任意路径产生大量时间序列
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// 数据库查询耗时的桶（秒），SQLite 查询通常在毫秒以内
+pub(crate) const DB_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("指标定义无效，请检查名称和标签"));
+
+/// 全局指标
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// 完成记录写入队列的指标
+pub struct WriterMetrics {
+    /// 已入队的记录数
+    pub enqueued: IntCounter,
+    /// 入队时队列已满、需要等待的次数
+    pub queue_full: IntCounter,
+    /// 已成功写入数据库的记录数
+    pub flushed: IntCounter,
+    /// 写入失败而丢弃的记录数
+    pub failed: IntCounter,
+    /// 写入失败后等待重试的次数
+    pub retries: IntCounter,
+    /// 已提交的事务数
+    pub batches: IntCounter,
+    /// 队列中等待写入的记录数，抓取时更新
+    pub queue_depth: IntGauge,
+}
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    completions: IntCounterVec,
+    db_duration: HistogramVec,
+    db_errors: IntCounterVec,
+    pool_connections: IntGaugeVec,
+    pool_max_connections: IntGauge,
+    pub writer: WriterMetrics,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("mind_pulse".to_owned()), None)?;
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP 请求数"),
+            &["route", "method", "status"],
+        )?;
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP 请求耗时（秒）"),
+            &["route", "method", "status"],
+        )?;
+        let completions = IntCounterVec::new(
+            Opts::new("completions_total", "写入数据库的完成记录数"),
+            &["scale", "client_type"],
+        )?;
+        let db_duration = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "数据库操作耗时（秒）")
+                .buckets(DB_BUCKETS.to_vec()),
+            &["operation"],
+        )?;
+        let db_errors = IntCounterVec::new(
+            Opts::new("db_errors_total", "数据库操作失败次数"),
+            &["operation"],
+        )?;
+        let pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "连接池中的连接数"),
+            &["state"],
+        )?;
+        let pool_max_connections = IntGauge::new("db_pool_max_connections", "连接池的最大连接数")?;
+
+        let writer = WriterMetrics {
+            enqueued: IntCounter::new("writer_enqueued_total", "已入队的完成记录数")?,
+            queue_full: IntCounter::new("writer_queue_full_total", "入队时队列已满的次数")?,
+            flushed: IntCounter::new("writer_flushed_total", "已写入数据库的完成记录数")?,
+            failed: IntCounter::new("writer_failed_total", "写入失败而丢弃的完成记录数")?,
+            retries: IntCounter::new("writer_retries_total", "写入失败后重试的次数")?,
+            batches: IntCounter::new("writer_batches_total", "已提交的写入事务数")?,
+            queue_depth: IntGauge::new("writer_queue_depth", "等待写入的完成记录数")?,
+        };
+
+        let build_info =
+            IntGaugeVec::new(Opts::new("build_info", "构建信息，值恒为 1"), &["version"])?;
+        build_info
+            .with_label_values(&[env!("CARGO_PKG_VERSION")])
+            .set(1);
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_duration.clone()))?;
+        registry.register(Box::new(completions.clone()))?;
+        registry.register(Box::new(db_duration.clone()))?;
+        registry.register(Box::new(db_errors.clone()))?;
+        registry.register(Box::new(pool_connections.clone()))?;
+        registry.register(Box::new(pool_max_connections.clone()))?;
+        registry.register(Box::new(writer.enqueued.clone()))?;
+        registry.register(Box::new(writer.queue_full.clone()))?;
+        registry.register(Box::new(writer.flushed.clone()))?;
+        registry.register(Box::new(writer.failed.clone()))?;
+        registry.register(Box::new(writer.retries.clone()))?;
+        registry.register(Box::new(writer.batches.clone()))?;
+        registry.register(Box::new(writer.queue_depth.clone()))?;
+        registry.register(Box::new(build_info))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_duration,
+            completions,
+            db_duration,
+            db_errors,
+            pool_connections,
+            pool_max_connections,
+            writer,
+        })
+    }
+
+    /// 记录一次 HTTP 请求，`route` 为路由模板
+    pub fn observe_request(&self, route: &str, method: &str, status: u16, duration: Duration) {
+        #[cfg(feature = "otel")]
+        if let Some(otel) = otel_metrics() {
+            otel.observe_request(route, method, status, duration);
+        }
+
+        let status = status.to_string();
+        let labels = [route, method, status.as_str()];
+
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_duration
+            .with_label_values(&labels)
+            .observe(duration.as_secs_f64());
+    }
+
+    /// 记录一次数据库操作
+    pub fn observe_db(&self, operation: &str, duration: Duration, success: bool) {
+        self.db_duration
+            .with_label_values(&[operation])
+            .observe(duration.as_secs_f64());
+
+        if !success {
+            self.db_errors.with_label_values(&[operation]).inc();
+        }
+
+        #[cfg(feature = "otel")]
+        if let Some(otel) = otel_metrics() {
+            otel.observe_db(operation, duration, success);
+        }
+    }
+
+    /// 记录一条写入数据库的完成记录
+    pub fn record_completion(&self, scale: &str, client_type: &str) {
+        self.completions
+            .with_label_values(&[scale, client_type])
+            .inc();
+
+        #[cfg(feature = "otel")]
+        if let Some(otel) = otel_metrics() {
+            otel.record_completion(scale, client_type);
+        }
+    }
+
+    fn encode(&self) -> prometheus::Result<String> {
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+/// 当前请求匹配的路由模板，由 `RouteLabel` 逐级拼接
+#[derive(Debug, Default)]
+struct RouteTemplate(String);
+
+/// 为路由记录模板路径，嵌套的路由依次拼接，如 `/api/v1` + `scales/{id}`
+pub struct RouteLabel(pub &'static str);
+
+#[async_trait]
+impl Handler for RouteLabel {
+    async fn handle(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        _res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let segment = self.0.trim_matches('/');
+
+        match depot.obtain_mut::<RouteTemplate>() {
+            Ok(template) => {
+                template.0.push('/');
+                template.0.push_str(segment);
+            }
+            Err(_) => {
+                depot.inject(RouteTemplate(format!("/{}", segment)));
+            }
+        }
+    }
+}
+
+/// 请求匹配的路由模板，没有匹配时返回固定的标签
+pub fn route_template(depot: &Depot) -> &str {
+    depot
+        .obtain::<RouteTemplate>()
+        .map(|t| t.0.as_str())
+        .unwrap_or(UNMATCHED_ROUTE)
+}
+
+/// Prometheus 抓取接口
+#[handler]
+pub async fn render_metrics(depot: &mut Depot, res: &mut Response) -> MindPulseResult<()> {
+    let metrics = metrics();
+
+    // 连接池和队列状态在抓取时读取
+    if let Ok(statistics) = obtain_statistics(depot) {
+        if let Some(pool) = statistics.pool_state() {
+            let idle = i64::from(pool.idle);
+            metrics
+                .pool_connections
+                .with_label_values(&["idle"])
+                .set(idle);
+            metrics
+                .pool_connections
+                .with_label_values(&["active"])
+                .set(i64::from(pool.size) - idle);
+            metrics.pool_max_connections.set(i64::from(pool.max));
+        }
+        metrics
+            .writer
+            .queue_depth
+            .set(statistics.queue_depth() as i64);
+    }
+
+    let body = metrics.encode().map_err(|e| {
+        error!(message = "Failed to encode metrics", error = ?e);
+        MindPulseError::Unavailable("指标不可用")
+    })?;
+
+    res.status_code(StatusCode::OK);
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(TEXT_FORMAT));
+    res.write_body(body).map_err(|e| {
+        error!(message = "Failed to write metrics", error = ?e);
+        MindPulseError::Unavailable("指标不可用")
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,470 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use salvo::{async_trait, Depot, FlowCtrl, Handler, Request, Response, Writer};
+use serde::{Deserialize, Serialize};
+
+use crate::client::client_address;
+use crate::error::{MindPulseError, MindPulseResult};
+
+/// 清理已回满的令牌桶的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 令牌桶规则：最多积攒 `capacity` 个令牌，每 `period` 补满
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitRule {
+    capacity: f64,
+    period: Duration,
+}
+
+impl RateLimitRule {
+    pub const fn new(capacity: u32, period: Duration) -> Self {
+        Self {
+            capacity: capacity as f64,
+            period,
+        }
+    }
+
+    /// 每秒补充的令牌数
+    fn refill_rate(&self) -> f64 {
+        self.capacity / self.period.as_secs_f64()
+    }
+}
+
+impl FromStr for RateLimitRule {
+    type Err = String;
+
+    /// 格式为 `次数/秒数`，如 `10/60`，两者都必须大于 0
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_once('/')
+            .and_then(|(capacity, seconds)| {
+                let capacity = capacity.trim().parse::<u32>().ok().filter(|c| *c > 0)?;
+                let seconds = seconds.trim().parse::<u64>().ok().filter(|s| *s > 0)?;
+                Some(Self::new(capacity, Duration::from_secs(seconds)))
+            })
+            .ok_or_else(|| format!("无效的限流规则 `{}`，应形如 `10/60`", s))
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// 令牌回满的时间，之后可以安全地移除
+    full_at: Instant,
+}
+
+impl Bucket {
+    fn full(rule: &RateLimitRule, now: Instant) -> Self {
+        Self {
+            tokens: rule.capacity,
+            updated: now,
+            full_at: now,
+        }
+    }
+
+    /// 尝试取出一个令牌，失败时返回需要等待的时间
+    fn take(&mut self, rule: &RateLimitRule, now: Instant) -> Result<(), Duration> {
+        let rate = rule.refill_rate();
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = f64::min(rule.capacity, self.tokens + elapsed * rate);
+        self.updated = now;
+
+        let result = if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        };
+
+        self.full_at = now + Duration::from_secs_f64((rule.capacity - self.tokens) / rate);
+
+        result
+    }
+}
+
+/// 持久化到文件中的令牌桶
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedBucket {
+    route: String,
+    client: String,
+    tokens: f64,
+    /// 更新时间，Unix 时间戳（秒）
+    updated: f64,
+    /// 回满时间，Unix 时间戳（秒）
+    full_at: f64,
+}
+
+/// 内存中的令牌桶存储，可选择在关闭时保存到文件并在启动时恢复
+pub struct RateLimitStore {
+    buckets: Mutex<HashMap<(&'static str, String), Bucket>>,
+    path: Option<PathBuf>,
+}
+
+impl RateLimitStore {
+    /// 创建存储，设置了状态文件时从该文件恢复状态，并启动清理任务
+    pub fn init(routes: &[&'static str], path: Option<PathBuf>) -> Arc<Self> {
+        let buckets = match &path {
+            Some(path) => Self::load(path, routes),
+            None => HashMap::new(),
+        };
+
+        let store = Arc::new(Self {
+            buckets: Mutex::new(buckets),
+            path,
+        });
+
+        let cleaned = store.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                cleaned.cleanup();
+            }
+        });
+
+        store
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(&'static str, String), Bucket>> {
+        // 令牌桶只是限流状态，锁中毒时继续使用即可
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn take(
+        &self,
+        route: &'static str,
+        client: &str,
+        rule: &RateLimitRule,
+    ) -> Result<(), Duration> {
+        let now = Instant::now();
+
+        self.lock()
+            .entry((route, client.to_owned()))
+            .or_insert_with(|| Bucket::full(rule, now))
+            .take(rule, now)
+    }
+
+    /// 移除已回满的令牌桶，避免内存无限增长
+    fn cleanup(&self) {
+        let now = Instant::now();
+        let mut buckets = self.lock();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| bucket.full_at > now);
+
+        let removed = before - buckets.len();
+        if removed > 0 {
+            debug!(
+                message = "Rate limit buckets cleaned up",
+                removed,
+                remaining = buckets.len()
+            );
+        }
+    }
+
+    fn load(path: &PathBuf, routes: &[&'static str]) -> HashMap<(&'static str, String), Bucket> {
+        let persisted: Vec<PersistedBucket> = match std::fs::read(path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(persisted) => persisted,
+                Err(e) => {
+                    warn!(message = "Failed to parse rate limit state", path = ?path, error = ?e);
+                    return HashMap::new();
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return HashMap::new(),
+            Err(e) => {
+                warn!(message = "Failed to read rate limit state", path = ?path, error = ?e);
+                return HashMap::new();
+            }
+        };
+
+        let now = Instant::now();
+        let unix_now = unix_seconds(SystemTime::now());
+        let to_instant = |secs: f64| {
+            let offset = Duration::from_secs_f64((secs - unix_now).abs());
+            if secs >= unix_now {
+                now + offset
+            } else {
+                now.checked_sub(offset).unwrap_or(now)
+            }
+        };
+
+        let buckets: HashMap<_, _> = persisted
+            .into_iter()
+            .filter(|b| b.full_at > unix_now)
+            .filter_map(|b| {
+                // 只恢复仍然存在的路由
+                let route = routes.iter().find(|r| **r == b.route)?;
+                Some((
+                    (*route, b.client),
+                    Bucket {
+                        tokens: b.tokens,
+                        updated: to_instant(b.updated),
+                        full_at: to_instant(b.full_at),
+                    },
+                ))
+            })
+            .collect();
+
+        info!(
+            message = "Rate limit state restored",
+            path = ?path,
+            buckets = buckets.len()
+        );
+
+        buckets
+    }
+
+    /// 将尚未回满的令牌桶保存到文件
+    pub fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let to_unix = |instant: Instant| {
+            if instant >= now {
+                unix_seconds(system_now + (instant - now))
+            } else {
+                unix_seconds(system_now - (now - instant))
+            }
+        };
+
+        let persisted: Vec<PersistedBucket> = self
+            .lock()
+            .iter()
+            .filter(|(_, bucket)| bucket.full_at > now)
+            .map(|((route, client), bucket)| PersistedBucket {
+                route: (*route).to_owned(),
+                client: client.clone(),
+                tokens: bucket.tokens,
+                updated: to_unix(bucket.updated),
+                full_at: to_unix(bucket.full_at),
+            })
+            .collect();
+
+        let result = serde_json::to_vec(&persisted)
+            .map_err(MindPulseError::from)
+            .and_then(|bytes| std::fs::write(path, bytes).map_err(MindPulseError::from));
+
+        match result {
+            Ok(()) => info!(
+                message = "Rate limit state persisted",
+                path = ?path,
+                buckets = persisted.len()
+            ),
+            Err(e) => {
+                error!(message = "Failed to persist rate limit state", path = ?path, error = ?e)
+            }
+        }
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// 被限流的请求，由 `Logger` 记录
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    pub route: &'static str,
+    pub client: String,
+}
+
+/// 按客户端地址限流的 hoop
+///
+/// 同一个 `route` 名称共享令牌桶，旧版路由和新版路由使用相同的名称，避免绕过限制。
+pub struct RateLimit {
+    route: &'static str,
+    rule: RateLimitRule,
+    deferred: bool,
+}
+
+impl RateLimit {
+    pub fn new(route: &'static str, rule: RateLimitRule) -> Self {
+        Self {
+            route,
+            rule,
+            deferred: false,
+        }
+    }
+
+    /// 由处理器在校验请求之后调用 [`take_deferred`] 扣除令牌，无效的请求不消耗令牌
+    pub fn deferred(route: &'static str, rule: RateLimitRule) -> Self {
+        Self {
+            deferred: true,
+            ..Self::new(route, rule)
+        }
+    }
+}
+
+/// 延后到处理器中执行的限流规则
+#[derive(Debug, Clone, Copy)]
+struct DeferredRateLimit {
+    route: &'static str,
+    rule: RateLimitRule,
+}
+
+fn obtain_store(depot: &Depot) -> MindPulseResult<&Arc<RateLimitStore>> {
+    depot.obtain::<Arc<RateLimitStore>>().map_err(|_| {
+        error!(message = "Rate limit store is missing from depot");
+        MindPulseError::Unavailable("限流服务不可用")
+    })
+}
+
+/// 为客户端扣除一个令牌，令牌不足时返回 `RateLimited`
+fn take(
+    route: &'static str,
+    rule: &RateLimitRule,
+    req: &Request,
+    depot: &mut Depot,
+) -> MindPulseResult<()> {
+    let store = obtain_store(depot)?.clone();
+    let client = client_address(req, depot);
+
+    store.take(route, &client, rule).map_err(|wait| {
+        depot.inject(RateLimited { route, client });
+        MindPulseError::RateLimited(retry_after(wait))
+    })
+}
+
+/// 扣除 [`RateLimit::deferred`] 登记的令牌，路由没有延后的限流时直接通过
+pub fn take_deferred(req: &Request, depot: &mut Depot) -> MindPulseResult<()> {
+    match depot.obtain::<DeferredRateLimit>() {
+        Ok(&DeferredRateLimit { route, rule }) => take(route, &rule, req, depot),
+        Err(_) => Ok(()),
+    }
+}
+
+/// `Retry-After` 的秒数，不足一秒的部分向上取整
+fn retry_after(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
+
+#[async_trait]
+impl Handler for RateLimit {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        if self.deferred {
+            depot.inject(DeferredRateLimit {
+                route: self.route,
+                rule: self.rule,
+            });
+            return;
+        }
+
+        if let Err(e) = take(self.route, &self.rule, req, depot) {
+            e.write(req, depot, res).await;
+            ctrl.skip_rest();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const ROUTE: &str = "completions";
+
+    fn assert_close(actual: Duration, expected: Duration, tolerance: Duration) {
+        let diff = actual.abs_diff(expected);
+        assert!(diff < tolerance, "{:?} != {:?}", actual, expected);
+    }
+
+    /// 只有浮点运算的误差
+    fn assert_exact(actual: Duration, expected: Duration) {
+        assert_close(actual, expected, Duration::from_millis(1));
+    }
+
+    /// 保存和恢复之间经过了真实时间
+    fn assert_restored(actual: Duration, expected: Duration) {
+        assert_close(actual, expected, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn bucket_allows_burst_then_refills() {
+        let rule = RateLimitRule::new(3, Duration::from_secs(30));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut bucket = Bucket::full(&rule, start);
+
+        for _ in 0..3 {
+            assert!(bucket.take(&rule, start).is_ok());
+        }
+        assert_exact(
+            bucket.take(&rule, start).unwrap_err(),
+            Duration::from_secs(10),
+        );
+        assert_exact(bucket.full_at - start, Duration::from_secs(30));
+
+        // 每 10 秒补充一个令牌
+        assert_exact(
+            bucket.take(&rule, at(5)).unwrap_err(),
+            Duration::from_secs(5),
+        );
+        assert!(bucket.take(&rule, at(10)).is_ok());
+        assert_exact(
+            bucket.take(&rule, at(10)).unwrap_err(),
+            Duration::from_secs(10),
+        );
+
+        // 空闲再久也最多积攒 `capacity` 个令牌
+        for _ in 0..3 {
+            assert!(bucket.take(&rule, at(3600)).is_ok());
+        }
+        assert!(bucket.take(&rule, at(3600)).is_err());
+    }
+
+    #[test]
+    fn retry_after_rounds_up() {
+        assert_eq!(retry_after(Duration::ZERO), 0);
+        assert_eq!(retry_after(Duration::from_millis(1)), 1);
+        assert_eq!(retry_after(Duration::from_secs(10)), 10);
+        assert_eq!(retry_after(Duration::from_millis(10_001)), 11);
+    }
+
+    #[tokio::test]
+    async fn state_survives_restart() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("rate_limit.json");
+        let rule = RateLimitRule::new(2, Duration::from_secs(60));
+
+        let store = RateLimitStore::init(&[ROUTE, "results"], Some(path.clone()));
+        for _ in 0..2 {
+            store.take(ROUTE, "10.0.0.1", &rule).unwrap();
+        }
+        store.take("results", "10.0.0.2", &rule).unwrap();
+        store.persist();
+
+        // 不再存在的路由不恢复
+        let restored = RateLimitStore::init(&[ROUTE], Some(path));
+        {
+            let buckets = restored.lock();
+            assert_eq!(buckets.len(), 1);
+            let bucket = &buckets[&(ROUTE, "10.0.0.1".to_owned())];
+            assert!(bucket.tokens < 0.01);
+            assert_restored(bucket.full_at - Instant::now(), Duration::from_secs(60));
+        }
+
+        assert_restored(
+            restored.take(ROUTE, "10.0.0.1", &rule).unwrap_err(),
+            Duration::from_secs(30),
+        );
+        assert!(restored.take(ROUTE, "10.0.0.2", &rule).is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+use salvo::oapi::naming::{self, FlexNamer};
+use salvo::oapi::swagger_ui::SwaggerUi;
+use salvo::oapi::{self, endpoint, OpenApi};
+use salvo::{Response, Router};
+
+use crate::backup::create_backup;
+use crate::config::RateLimitSettings;
+use crate::deprecation::Deprecated;
+use crate::health::{healthz, readyz};
+use crate::metrics::{render_metrics, RouteLabel};
+use crate::rate_limit::RateLimit;
+use crate::scale::{
+    get_scale, get_scale_by_slug, item, list, list_scales, lookup_scales, score_scale,
+};
+use crate::statistics::{
+    create_completion, get_statistics, handle_get_statistics, handle_insert_record,
+};
+
+/// 提交完成记录的限流名称，新旧路由共享
+const COMPLETIONS: &str = "completions";
+/// 提交答案计分的限流名称
+const RESULTS: &str = "results";
+
+/// 所有限流名称，用于恢复持久化的限流状态
+pub const RATE_LIMITED_ROUTES: &[&str] = &[COMPLETIONS, RESULTS];
+
+/// 服务器版本号
+#[endpoint(responses((status_code = 200, description = "版本号", body = String, content_type = "text/plain")))]
+async fn version(res: &mut Response) {
+    let v = env!("CARGO_PKG_VERSION");
+    res.render(v);
+}
+
+/// 带有指标标签的路由，标签为路由模板而不是实际路径
+fn route(path: &'static str) -> Router {
+    Router::with_path(path).hoop(RouteLabel(path))
+}
+
+/// `/api/v1` 下的路由
+fn api_v1(limits: &RateLimitSettings) -> Router {
+    route("api/v1")
+        .push(route("version").get(version))
+        .push(
+            route("scales")
+                .get(list_scales)
+                .push(route("lookup").get(lookup_scales))
+                .push(route("by-slug/{slug}").get(get_scale_by_slug))
+                .push(route("{id}").get(get_scale))
+                .push(
+                    route("{id}/results")
+                        .hoop(RateLimit::new(RESULTS, limits.results))
+                        .post(score_scale),
+                ),
+        )
+        .push(
+            route("completions")
+                .hoop(RateLimit::deferred(COMPLETIONS, limits.completions))
+                .post(create_completion),
+        )
+        .push(route("statistics").get(get_statistics))
+}
+
+/// 旧版路由，保留兼容，响应中带有 `Deprecation` 头
+fn legacy(limits: &RateLimitSettings) -> Router {
+    Router::new()
+        .push(
+            route("version")
+                .hoop(Deprecated::new("/api/v1/version"))
+                .get(version),
+        )
+        .push(
+            route("list")
+                .hoop(Deprecated::new("/api/v1/scales"))
+                .get(list),
+        )
+        .push(
+            route("scales")
+                .hoop(Deprecated::new("/api/v1/scales"))
+                .get(list),
+        )
+        .push(
+            route("scales/{id}")
+                .hoop(Deprecated::new("/api/v1/scales/{id}"))
+                .get(item),
+        )
+        .push(
+            route("statistics")
+                .hoop(Deprecated::new("/api/v1/completions"))
+                .hoop(RateLimit::deferred(COMPLETIONS, limits.completions))
+                .get(handle_insert_record),
+        )
+        .push(
+            route("get_statistics")
+                .hoop(Deprecated::new("/api/v1/statistics"))
+                .get(handle_get_statistics),
+        )
+}
+
+/// 创建完整路由，包含 OpenAPI 文档
+///
+/// `admin` 为真时开放需要令牌的管理接口。
+pub fn create_router(limits: &RateLimitSettings, admin: bool) -> Router {
+    let router = Router::new().push(api_v1(limits)).push(legacy(limits));
+
+    // 文档中的类型名不带模块路径
+    naming::set_namer(FlexNamer::new().short_mode(true));
+    let mut doc = OpenApi::new("心灵脉冲 API", env!("CARGO_PKG_VERSION")).merge_router(&router);
+
+    // 不在 /api/v1 下的都是旧版路由
+    for (path, path_item) in doc.paths.iter_mut() {
+        if !path.starts_with("/api/v1/") {
+            for operation in path_item.operations.values_mut() {
+                operation.deprecated = Some(oapi::Deprecated::True);
+            }
+        }
+    }
+
+    // 探针、指标和管理接口不属于 API，不出现在文档中
+    let router = if admin {
+        router.unshift(route("admin/backup").post(create_backup))
+    } else {
+        router
+    };
+
+    router
+        .unshift(route("healthz").get(healthz))
+        .unshift(route("readyz").get(readyz))
+        .unshift(route("metrics").get(render_metrics))
+        .unshift(
+            doc.into_router("/api-doc/openapi.json")
+                .hoop(RouteLabel("api-doc/openapi.json")),
+        )
+        .unshift(
+            SwaggerUi::new("/api-doc/openapi.json")
+                .into_router("/swagger-ui")
+                .hoop(RouteLabel("swagger-ui")),
+        )
+}
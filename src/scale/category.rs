@@ -1,9 +1,10 @@
-use serde::Serialize;
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
 
 /// 心理学自评量表的顶层分类枚举（覆盖全面、结构稳定）
 /// 每个变体下方注释列出该类别的经典量表示例（中文名 + 英文名 + 说明），
 /// 所有量表均为公开使用、有中文版、且经信效度验证。
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ScaleCategory {
     /// 人格特质
@@ -1,3 +1,4 @@
+use salvo::oapi::ToSchema;
 use serde::Serialize;
 
 use crate::scale::category::ScaleCategory;
@@ -106,6 +107,8 @@ pub struct Scale<'r, I, Q> {
     pub related_categories: Option<&'r [ScaleCategory]>,
     /// 简称
     pub abbreviation: PlainText,
+    /// 稳定的英文标识，用于 URL，只能包含小写字母、数字和 `-`
+    pub slug: PlainText,
 
     /// 对量表的说明
     pub instruction: Texts,
@@ -145,7 +148,7 @@ pub enum Status {
     Major,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Tag {
     pub info: Option<PlainTexts>,
     pub normal: Option<PlainTexts>,
@@ -153,6 +156,16 @@ pub struct Tag {
     pub error: Option<PlainTexts>,
 }
 
+impl Tag {
+    /// 任意一组标签中包含 `tag`
+    pub(crate) fn contains(&self, tag: &str) -> bool {
+        [self.info, self.normal, self.warning, self.error]
+            .into_iter()
+            .flatten()
+            .any(|tags| tags.contains(&tag))
+    }
+}
+
 /// 特征
 #[derive(Debug, Serialize)]
 pub struct Characteristic {
@@ -160,7 +173,7 @@ pub struct Characteristic {
     pub high: PlainTexts,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(tag = "type", content = "text", rename_all = "UPPERCASE")]
 pub enum HTMLElement {
     Strong(PlainText),
@@ -168,7 +181,7 @@ pub enum HTMLElement {
     //A { text: PlainText, href: PlainText },
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(untagged)]
 pub enum SentenceItem {
     Plain(PlainText),
@@ -178,11 +191,15 @@ pub enum SentenceItem {
 pub type Sentence = &'static [SentenceItem];
 pub type Texts = &'static [Sentence];
 
-#[derive(Debug, Serialize)]
-pub struct ScaleListItem<'r> {
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScaleListItem {
     pub id: u16,
     /// 名称
     pub name: PlainText,
+    /// 缩写
+    pub abbreviation: PlainText,
+    /// 稳定的英文标识
+    pub slug: PlainText,
     /// 预计测试时长（分钟）
     pub duration: [u32; 2],
     /// 问题总数
@@ -191,7 +208,7 @@ pub struct ScaleListItem<'r> {
     pub primary_category: ScaleCategory,
     /// 相关分类
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub related_categories: Option<&'r [ScaleCategory]>,
+    pub related_categories: Option<&'static [ScaleCategory]>,
     /// 一句话描述
     pub description: PlainText,
     /// 简介
@@ -199,13 +216,13 @@ pub struct ScaleListItem<'r> {
     /// 警告
     pub warning: Option<PlainText>,
     /// 标签
-    pub tags: &'r Tag,
+    pub tags: &'static Tag,
     /// 是否禁用
     pub disabled: bool,
 }
 
-impl ScaleListItem<'_> {
-    pub(crate) fn name(&self) -> &str {
+impl ScaleListItem {
+    pub(crate) fn name(&self) -> &'static str {
         self.name
     }
 
@@ -0,0 +1,165 @@
+use salvo::oapi::{
+    endpoint,
+    extract::{PathParam, QueryParam},
+};
+use salvo::{Depot, Request, Response, Writer};
+
+use crate::error::{MindPulseResult, Problem};
+use crate::extract::JsonBody;
+use crate::statistics::obtain_statistics;
+use crate::JsonRender;
+
+use super::{
+    get_scale_id_by_slug,
+    payload::{render_scale, scale_payload, ScaleView},
+    query::ScaleListQuery,
+    result::{ScaleAnswers, ScaleResult},
+    ScaleListItem, LIST,
+};
+
+/// 量表列表概览，支持筛选、搜索和排序
+#[endpoint(
+    tags("scales"),
+    status_codes(200, 400, 503),
+    responses((status_code = 200, description = "量表列表", body = [ScaleListItem]))
+)]
+pub async fn list_scales(
+    query: ScaleListQuery,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    let items = if query.needs_popularity() {
+        let statistics = obtain_statistics(depot)?;
+        query.apply(|id| statistics.completions(id))
+    } else {
+        query.apply(|_| 0)
+    };
+
+    debug!(message = "Scale list filtered", query = ?query, count = items.len());
+
+    res.json(items);
+
+    Ok(())
+}
+
+/// 根据 ID 获取量表的问卷
+///
+/// 只包含说明、题目和选项，不含选项分值和结果解释，提交答案后通过计分接口获取。
+#[endpoint(
+    tags("scales"),
+    status_codes(200, 304, 400, 404, 503),
+    parameters(("id", description = "量表 ID")),
+    responses(
+        (status_code = 200, description = "量表问卷，结构因量表而异", body = serde_json::Value),
+        (status_code = 304, description = "内容未变化"),
+    )
+)]
+pub async fn get_scale(
+    id: PathParam<u16>,
+    req: &mut Request,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    render_scale(*id, ScaleView::Questionnaire, req, res)
+}
+
+/// 根据 slug 获取量表的问卷
+#[endpoint(
+    tags("scales"),
+    status_codes(200, 304, 404, 503),
+    parameters(("slug", description = "量表的英文标识，如 `zung-sds`")),
+    responses(
+        (status_code = 200, description = "量表问卷，结构因量表而异", body = serde_json::Value),
+        (status_code = 304, description = "内容未变化"),
+    )
+)]
+pub async fn get_scale_by_slug(
+    slug: PathParam<String>,
+    req: &mut Request,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    let id = get_scale_id_by_slug(&slug)?;
+    render_scale(id, ScaleView::Questionnaire, req, res)
+}
+
+/// 根据缩写查找量表
+///
+/// 缩写可能重复，如霍兰德职业兴趣测评和抑郁自评量表都叫 SDS，因此返回所有候选量表。
+#[endpoint(
+    tags("scales"),
+    parameters(("abbr", description = "量表缩写，不区分大小写")),
+    responses(
+        (status_code = 200, description = "候选量表列表", body = [ScaleListItem]),
+        (status_code = 400, description = "缺少缩写", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn lookup_scales(abbr: QueryParam<String>, res: &mut Response) {
+    let candidates: Vec<&ScaleListItem> = LIST
+        .iter()
+        .filter(|p| p.abbreviation.eq_ignore_ascii_case(abbr.trim()))
+        .collect();
+
+    debug!(
+        message = "Scale lookup by abbreviation",
+        abbr = abbr.as_str(),
+        count = candidates.len()
+    );
+
+    res.json(candidates);
+}
+
+/// 提交答案并获取计分结果
+///
+/// 返回原始分、按量表规则换算后的得分、各维度得分和对应的结果解释，不返回每道题的得分。
+#[endpoint(
+    tags("scales"),
+    status_codes(200, 400, 404, 422, 429, 503),
+    parameters(("id", description = "量表 ID")),
+    responses((status_code = 200, description = "计分结果", body = ScaleResult))
+)]
+pub async fn score_scale(
+    id: PathParam<u16>,
+    body: JsonBody<ScaleAnswers>,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    let result = scale_payload(*id)?
+        .scoring()
+        .score(*id, &body.answers)
+        .map_err(|e| {
+            debug!(message = "Rejected scale answers", id = *id, error = ?e);
+            e
+        })?;
+    res.json(result);
+
+    Ok(())
+}
+
+/// 所有量表的列表概览（旧版）
+///
+/// 已弃用，请使用 `GET /api/v1/scales`。
+#[endpoint(
+    tags("legacy"),
+    responses((status_code = 200, description = "量表列表", body = [ScaleListItem]))
+)]
+pub async fn list(res: &mut Response) {
+    res.json(LIST);
+}
+
+/// 根据 ID 获取量表的完整数据（旧版）
+///
+/// 已弃用，请使用 `GET /api/v1/scales/{id}`。
+#[endpoint(
+    tags("legacy"),
+    status_codes(200, 304, 400, 404, 503),
+    parameters(("id", description = "量表 ID")),
+    responses(
+        (status_code = 200, description = "量表完整数据，结构因量表而异", body = serde_json::Value),
+        (status_code = 304, description = "内容未变化"),
+    )
+)]
+pub async fn item(
+    id: PathParam<u16>,
+    req: &mut Request,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    render_scale(*id, ScaleView::Full, req, res)
+}
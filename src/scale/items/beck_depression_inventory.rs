@@ -31,6 +31,7 @@ pub const BECK_DEPRESSION_INVENTORY: Scale<&[InterpretationItem<u8>], Question>
     primary_category: ScaleCategory::Emotion,
     related_categories: Some(&[ScaleCategory::MentalHealth, ScaleCategory::Somatic]),
     abbreviation: "BDI",
+    slug: "bdi",
     introduction: INTRODUCTION,
     instruction: INSTRUCTION,
     idea: None,
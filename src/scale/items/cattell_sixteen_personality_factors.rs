@@ -695,6 +695,7 @@ pub const SIXTEEN_PERSONALITY_FACTORS: Scale<Interpretation, Question> = Scale {
     name: "卡特尔16种人格因素问卷",
     description: "16个维度，一眼看懂你独一无二的人格特征",
     abbreviation: "16PF",
+    slug: "16pf",
     primary_category: ScaleCategory::Personality,
     related_categories: Some(&[
         ScaleCategory::CognitionAndAbility,
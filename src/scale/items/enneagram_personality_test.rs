@@ -94,6 +94,7 @@ pub const ENNEAGRAM_PERSONALITY_TEST: Scale<Interpretation, Question> = Scale {
         ScaleCategory::AttitudeAndValues,
     ]),
     abbreviation: "EPT",
+    slug: "enneagram",
     introduction: INTRODUCTION,
     instruction: INSTRUCTION,
     idea: Some(&[
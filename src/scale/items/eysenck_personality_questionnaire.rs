@@ -686,6 +686,7 @@ pub const EYSENCK_PERSONALITY_QUESTIONNAIRE_REVISED_SHORT_SCALE: Scale<Interpret
     primary_category: ScaleCategory::Personality,
     related_categories: Some(&[ScaleCategory::Emotion, ScaleCategory::MentalHealth]),
     abbreviation: "EPQ-RSC",
+    slug: "epq-rsc",
     introduction: INTRODUCTION,
     instruction: INSTRUCTION,
     idea: Some(&[
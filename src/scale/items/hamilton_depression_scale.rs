@@ -27,6 +27,7 @@ pub const HAMILTON_DEPRESSION_SCALE: Scale<&[InterpretationItem], Question> = Sc
     name: "汉密尔顿抑郁量表",
     description: "他评量表，用于评估抑郁状态",
     abbreviation: "HAMD",
+    slug: "hamd",
     primary_category: ScaleCategory::Emotion,
     related_categories: Some(&[ScaleCategory::MentalHealth, ScaleCategory::Somatic]),
     idea: None,
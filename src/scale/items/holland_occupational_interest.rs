@@ -429,6 +429,7 @@ pub const HOLLAND_OCCUPATIONAL_INTEREST: Scale<Interpretation, Question> = Scale
         ScaleCategory::AttitudeAndValues,
     ]),
     abbreviation: "SDS", // 与抑郁自评量表缩写相同
+    slug: "holland-sds",
     introduction: INTRODUCTION,
     instruction: INSTRUCTION,
     idea: Some(&[
@@ -684,6 +684,7 @@ pub const HOLLAND_OCCUPATIONAL_INTEREST_HIGH_SCHOOL_CN: Scale<Interpretation, Qu
         ScaleCategory::AttitudeAndValues,
     ]),
     abbreviation: "SDS", // 与抑郁自评量表缩写相同
+    slug: "holland-sds-high-school",
     introduction: INTRODUCTION,
     instruction: INSTRUCTION,
     idea: Some(&[
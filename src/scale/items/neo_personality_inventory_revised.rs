@@ -2881,6 +2881,7 @@ pub const NEO_PERSONALITY_INVENTORY_REVISED: Scale<Interpretation, Question> = S
     id: 1,
     name: "大五人格测试",
     abbreviation: "NEO-PI-R",
+    slug: "neo-pi-r",
     description: "五大维度，一眼看懂你独一无二的性格底色",
     primary_category: ScaleCategory::Personality,
     related_categories: Some(&[
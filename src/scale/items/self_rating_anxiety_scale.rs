@@ -27,6 +27,7 @@ pub const SELF_RATING_ANXIETY_SCALE: Scale<&[InterpretationItem<u8>], Question>
     name: "焦虑自评量表",
     description: "用科学方式读懂自己的情绪，为内心的平静提供清晰指引",
     abbreviation: "SAS",
+    slug: "zung-sas",
     primary_category: ScaleCategory::Emotion,
     related_categories: Some(&[ScaleCategory::MentalHealth]),
     introduction: INTRODUCTION,
@@ -27,6 +27,7 @@ pub const SELF_RATING_DEPRESSION_SCALE: Scale<&[InterpretationItem<u8>], Questio
     name: "抑郁自评量表",
     description: "快速自测情绪状态，用20个问题看清你是否被抑郁悄悄困扰",
     abbreviation: "SDS",
+    slug: "zung-sds",
     primary_category: ScaleCategory::Emotion,
     related_categories: Some(&[ScaleCategory::MentalHealth]),
     introduction: INTRODUCTION,
@@ -447,6 +447,7 @@ pub const SYMPTOM_CHECKLIST_90: Scale<Interpretation, Question> = Scale {
     name: "症状自评量表",
     description: "像体检一样简单，为你的心理健康做一次全面快筛",
     abbreviation: "SCL-90",
+    slug: "scl-90",
     primary_category: ScaleCategory::MentalHealth,
     related_categories: Some(&[
         ScaleCategory::Emotion,
@@ -145,6 +145,7 @@ pub const YALE_BROWN_OBSESSIVE_COMPULSIVE_SCALE: Scale<&[InterpretationItem], Qu
     name: "耶鲁布朗强迫症量表",
     description: "专为强迫症设计的科学自测工具，10题看清困扰程度",
     abbreviation: "Y-BOCS",
+    slug: "y-bocs",
     primary_category: ScaleCategory::Emotion,
     related_categories: Some(&[
         ScaleCategory::MentalHealth,
@@ -1,9 +1,21 @@
 mod category;
 mod common;
+mod handler;
 mod items;
+mod payload;
+mod query;
+mod result;
+mod validate;
 
 use crate::error::{MindPulseError, MindPulseResult};
 
+pub use self::common::ScaleListItem;
+pub use self::handler::{
+    get_scale, get_scale_by_slug, item, list, list_scales, lookup_scales, score_scale,
+};
+pub use self::payload::{init_scale_payloads, loaded_scale_payloads};
+pub use self::validate::validate_scales;
+
 pub use self::items::{
     BECK_DEPRESSION_INVENTORY, ENNEAGRAM_PERSONALITY_TEST,
     EYSENCK_PERSONALITY_QUESTIONNAIRE_REVISED_SHORT_SCALE, HAMILTON_DEPRESSION_SCALE,
@@ -34,6 +46,69 @@ pub fn get_scale_name_by_id(id: u16) -> MindPulseResult<&'static str> {
     get_scale_info_by_id(id).map(|(_, name, _)| name)
 }
 
+/// 根据 slug 查找量表 ID
+pub fn get_scale_id_by_slug(slug: &str) -> MindPulseResult<u16> {
+    LIST.iter()
+        .find(|p| p.slug == slug)
+        .map(|p| p.id)
+        .ok_or_else(|| MindPulseError::SlugNotFound(slug.to_owned()))
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+const fn slug_is_valid(slug: &str) -> bool {
+    let bytes = slug.as_bytes();
+    if bytes.is_empty() || bytes[0] == b'-' || bytes[bytes.len() - 1] == b'-' {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if !matches!(bytes[i], b'a'..=b'z' | b'0'..=b'9' | b'-') {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+/// slug 只能包含小写字母、数字和 `-`，且不能重复
+const fn slugs_are_valid(items: &[ScaleListItem]) -> bool {
+    let mut i = 0;
+    while i < items.len() {
+        if !slug_is_valid(items[i].slug) {
+            return false;
+        }
+
+        let mut j = i + 1;
+        while j < items.len() {
+            if str_eq(items[i].slug, items[j].slug) {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+
+    true
+}
+
 /// 该宏用于注册量表，自动生成列表、查找函数和 JSON 序列化逻辑。
 /// 避免手动维护多个 match 分支和数组。
 macro_rules! register_scales {
@@ -43,14 +118,14 @@ macro_rules! register_scales {
             $scale:ident $( => { disabled: $disabled:expr } )?
         ),* $(,)?
     ) => {
-        use crate::scale::common::ScaleListItem;
-
         /// 所有量表的列表概览（用于前端展示列表）
         pub const LIST: &[ScaleListItem] = &[
             $(
                 ScaleListItem {
                     id: $scale.id,
                     name: $scale.name,
+                    abbreviation: $scale.abbreviation,
+                    slug: $scale.slug,
                     // 自动计算时长
                     duration: estimated_duration($scale.questions.len() as u32),
                     total_questions: $scale.questions.len() as u32,
@@ -68,16 +143,18 @@ macro_rules! register_scales {
             ),*
         ];
 
-        /// 根据 ID 获取量表的完整 JSON 数据
-        pub fn get_scale_json_by_id(id: u16) -> MindPulseResult<serde_json::Value> {
+        // 编译期检查 slug 的格式和唯一性
+        const _: () = assert!(slugs_are_valid(LIST), "量表 slug 格式错误或重复");
+
+        /// 根据 ID 将量表的完整数据序列化为 JSON
+        pub fn serialize_scale_by_id(id: u16) -> MindPulseResult<Vec<u8>> {
             match id {
                 $(
                     val if val == $scale.id => {
-                        serde_json::to_value(&$scale)
-                            .map_err(|e| MindPulseError::Response(format!("序列化失败: {}", e)))
+                        serde_json::to_vec(&$scale).map_err(MindPulseError::from)
                     }
                 )*
-                _ => Err(MindPulseError::Response("无效的量表 ID".to_owned())),
+                _ => Err(MindPulseError::ScaleNotFound(id)),
             }
         }
 
@@ -87,7 +164,7 @@ macro_rules! register_scales {
                 $(
                     val if val == $scale.id => Ok(($scale.id, $scale.name, $scale.questions.len())),
                 )*
-                _ => Err(MindPulseError::Response("无效的量表 ID".to_owned())),
+                _ => Err(MindPulseError::ScaleNotFound(id)),
             }
         }
     };
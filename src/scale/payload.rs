@@ -0,0 +1,315 @@
+use std::{collections::HashMap, io::Write, sync::OnceLock};
+
+use brotli::enc::BrotliEncoderParams;
+use flate2::{write::GzEncoder, Compression};
+use salvo::{
+    http::{
+        header::{
+            ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH,
+            VARY,
+        },
+        HeaderValue, StatusCode,
+    },
+    Request, Response,
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::{MindPulseError, MindPulseResult};
+
+use super::{result::ScoringKey, serialize_scale_by_id, LIST};
+
+/// 量表数据只随版本发布变化，允许客户端缓存一天，过期后通过 ETag 重新验证
+const CACHE_CONTROL_VALUE: &str = "public, max-age=86400";
+
+/// brotli 最高压缩等级
+const BROTLI_QUALITY: i32 = 11;
+/// brotli 窗口大小（以 2 为底的对数）
+const BROTLI_WINDOW: i32 = 22;
+
+static PAYLOADS: OnceLock<HashMap<u16, ScalePayload>> = OnceLock::new();
+
+/// 响应体的编码方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+    Identity,
+}
+
+impl Encoding {
+    fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Encoding::Brotli => Some("br"),
+            Encoding::Gzip => Some("gzip"),
+            Encoding::Identity => None,
+        }
+    }
+
+    /// 不同编码的内容字节不同，强校验 ETag 也需要区分
+    fn etag_suffix(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "-br",
+            Encoding::Gzip => "-gzip",
+            Encoding::Identity => "",
+        }
+    }
+
+    /// 根据 `Accept-Encoding` 选择编码，q 值相同时优先 brotli
+    ///
+    /// `*` 只作用于没有单独列出的编码，`br;q=0, *` 仍然不接受 brotli。
+    fn negotiate(req: &Request) -> Self {
+        let mut brotli = None;
+        let mut gzip = None;
+        let mut any = None;
+
+        for value in req
+            .headers()
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+        {
+            for item in value.split(',') {
+                let mut parts = item.split(';').map(str::trim);
+                let name = parts.next().unwrap_or_default().to_ascii_lowercase();
+                let q = parts
+                    .find_map(|p| p.strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                match name.as_str() {
+                    "br" => brotli = Some(q),
+                    "gzip" | "x-gzip" => gzip = Some(q),
+                    "*" => any = Some(q),
+                    _ => {}
+                }
+            }
+        }
+
+        let brotli = brotli.or(any).unwrap_or(0.0);
+        let gzip = gzip.or(any).unwrap_or(0.0);
+
+        if brotli > 0.0 && brotli >= gzip {
+            Encoding::Brotli
+        } else if gzip > 0.0 {
+            Encoding::Gzip
+        } else {
+            Encoding::Identity
+        }
+    }
+}
+
+/// 预先序列化并压缩的响应体
+struct EncodedBody {
+    identity: Vec<u8>,
+    gzip: Vec<u8>,
+    brotli: Vec<u8>,
+    /// 内容 SHA-256 的前 16 字节
+    hash: String,
+}
+
+impl EncodedBody {
+    fn new(identity: Vec<u8>) -> MindPulseResult<Self> {
+        let digest = Sha256::digest(&identity);
+        let hash = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&identity)?;
+        let gzip = encoder.finish()?;
+
+        let params = BrotliEncoderParams {
+            quality: BROTLI_QUALITY,
+            lgwin: BROTLI_WINDOW,
+            ..Default::default()
+        };
+        let mut brotli = Vec::new();
+        brotli::BrotliCompress(&mut identity.as_slice(), &mut brotli, &params)?;
+
+        Ok(Self {
+            identity,
+            gzip,
+            brotli,
+            hash,
+        })
+    }
+
+    fn body(&self, encoding: Encoding) -> &[u8] {
+        match encoding {
+            Encoding::Brotli => &self.brotli,
+            Encoding::Gzip => &self.gzip,
+            Encoding::Identity => &self.identity,
+        }
+    }
+
+    fn etag(&self, encoding: Encoding) -> String {
+        format!("\"{}{}\"", self.hash, encoding.etag_suffix())
+    }
+
+    /// 请求中的 `If-None-Match` 是否与当前内容匹配
+    ///
+    /// 各编码的内容语义相同，命中其中任意一个都视为未变化。
+    fn matches(&self, req: &Request) -> bool {
+        let etags = [Encoding::Brotli, Encoding::Gzip, Encoding::Identity].map(|e| self.etag(e));
+
+        req.headers()
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            // If-None-Match 使用弱比较
+            .any(|tag| {
+                tag == "*"
+                    || etags
+                        .iter()
+                        .any(|etag| tag.trim_start_matches("W/") == etag)
+            })
+    }
+}
+
+/// 量表的不同视图
+#[derive(Debug, Clone, Copy)]
+pub enum ScaleView {
+    /// 完整数据，仅供旧版接口使用
+    Full,
+    /// 答题所需的问卷，不含分值和解释
+    Questionnaire,
+}
+
+/// 启动时准备好的量表数据
+pub struct ScalePayload {
+    full: EncodedBody,
+    questionnaire: EncodedBody,
+    scoring: ScoringKey,
+}
+
+impl ScalePayload {
+    fn new(full: Vec<u8>) -> MindPulseResult<Self> {
+        let value: Value = serde_json::from_slice(&full)?;
+        let (questionnaire, scoring) = ScoringKey::split(value);
+
+        Ok(Self {
+            full: EncodedBody::new(full)?,
+            questionnaire: EncodedBody::new(serde_json::to_vec(&questionnaire)?)?,
+            scoring,
+        })
+    }
+
+    fn view(&self, view: ScaleView) -> &EncodedBody {
+        match view {
+            ScaleView::Full => &self.full,
+            ScaleView::Questionnaire => &self.questionnaire,
+        }
+    }
+
+    pub(super) fn scoring(&self) -> &ScoringKey {
+        &self.scoring
+    }
+}
+
+/// 在启动时以最高等级序列化并压缩所有量表，之后的请求直接返回缓存的字节
+pub fn init_scale_payloads() -> MindPulseResult<()> {
+    let mut payloads = HashMap::with_capacity(LIST.len());
+    let (mut identity, mut gzip, mut brotli) = (0, 0, 0);
+
+    for item in LIST {
+        let payload = ScalePayload::new(serialize_scale_by_id(item.id())?).map_err(|e| {
+            error!(message = "Failed to compress scale payload", id = item.id(), error = ?e);
+            e
+        })?;
+
+        identity += payload.full.identity.len();
+        gzip += payload.full.gzip.len();
+        brotli += payload.full.brotli.len();
+        payloads.insert(item.id(), payload);
+    }
+
+    // 重复初始化时保留第一次的结果，内容相同
+    let _ = PAYLOADS.set(payloads);
+
+    info!(
+        message = "Scale payloads precomputed",
+        scales = LIST.len(),
+        bytes = identity,
+        gzip_bytes = gzip,
+        brotli_bytes = brotli
+    );
+
+    Ok(())
+}
+
+/// 量表数据是否已加载，返回加载的量表数量
+pub fn loaded_scale_payloads() -> Option<usize> {
+    PAYLOADS.get().map(HashMap::len)
+}
+
+pub(super) fn scale_payload(id: u16) -> MindPulseResult<&'static ScalePayload> {
+    let payloads = PAYLOADS.get().ok_or_else(|| {
+        error!(message = "Scale payloads are not initialized");
+        MindPulseError::Unavailable("量表数据尚未加载")
+    })?;
+
+    payloads.get(&id).ok_or(MindPulseError::ScaleNotFound(id))
+}
+
+/// 返回量表数据，按 `Accept-Encoding` 选择预压缩的版本，内容未变化时返回 304
+pub fn render_scale(
+    id: u16,
+    view: ScaleView,
+    req: &Request,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    let payload = scale_payload(id)?.view(view);
+    let encoding = Encoding::negotiate(req);
+
+    let headers = res.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&payload.etag(encoding)) {
+        headers.insert(ETAG, etag);
+    }
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL_VALUE));
+    headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+
+    if payload.matches(req) {
+        debug!(message = "Scale payload not modified", id);
+        res.status_code(StatusCode::NOT_MODIFIED);
+        return Ok(());
+    }
+
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/json; charset=utf-8"),
+    );
+    if let Some(content_encoding) = encoding.content_encoding() {
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(content_encoding));
+    }
+    res.body(payload.body(encoding));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: &str) -> Encoding {
+        let mut req = Request::new();
+        req.headers_mut()
+            .insert(ACCEPT_ENCODING, accept.parse().unwrap());
+        Encoding::negotiate(&req)
+    }
+
+    #[test]
+    fn prefers_brotli_on_tie() {
+        assert_eq!(negotiate("gzip, br"), Encoding::Brotli);
+        assert_eq!(negotiate("br;q=0.5, gzip"), Encoding::Gzip);
+        assert_eq!(negotiate("identity"), Encoding::Identity);
+    }
+
+    #[test]
+    fn wildcard_does_not_override_explicit_codings() {
+        assert_eq!(negotiate("br;q=0, *"), Encoding::Gzip);
+        assert_eq!(negotiate("*, br;q=0"), Encoding::Gzip);
+        assert_eq!(negotiate("br;q=0, gzip;q=0, *"), Encoding::Identity);
+        assert_eq!(negotiate("gzip;q=0.2, *;q=0.8"), Encoding::Brotli);
+    }
+}
@@ -0,0 +1,90 @@
+use salvo::oapi::{ToParameters, ToSchema};
+use serde::Deserialize;
+
+use super::{category::ScaleCategory, ScaleListItem, LIST};
+
+/// 量表列表的排序方式
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleSort {
+    /// 按完成次数从多到少
+    Popularity,
+    /// 按预计时长从短到长
+    Duration,
+    /// 按名称
+    Name,
+}
+
+/// 量表列表的筛选条件
+#[derive(Debug, Default, Deserialize, ToParameters)]
+#[salvo(parameters(default_parameter_in = Query))]
+pub struct ScaleListQuery {
+    /// 分类，匹配主分类或相关分类
+    pub category: Option<ScaleCategory>,
+    /// 标签，匹配任意一组标签
+    pub tag: Option<String>,
+    /// 是否包含已禁用的量表，默认不包含
+    pub include_disabled: Option<bool>,
+    /// 预计时长上限（分钟）
+    pub max_duration: Option<u32>,
+    /// 关键词，在名称、缩写和描述中查找，不区分大小写
+    pub q: Option<String>,
+    /// 排序方式，默认保持注册顺序
+    pub sort: Option<ScaleSort>,
+}
+
+impl ScaleListQuery {
+    /// 需要统计数据时才读取完成次数
+    pub fn needs_popularity(&self) -> bool {
+        matches!(self.sort, Some(ScaleSort::Popularity))
+    }
+
+    /// 按条件筛选并排序量表，`completions` 用于按热度排序
+    pub fn apply(&self, completions: impl Fn(u16) -> u64) -> Vec<&'static ScaleListItem> {
+        let keyword = self
+            .q
+            .as_deref()
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .map(str::to_lowercase);
+
+        let mut items: Vec<&'static ScaleListItem> = LIST
+            .iter()
+            .filter(|item| self.include_disabled.unwrap_or_default() || !item.disabled)
+            .filter(|item| match &self.category {
+                Some(category) => {
+                    item.primary_category == *category
+                        || item
+                            .related_categories
+                            .is_some_and(|related| related.contains(category))
+                }
+                None => true,
+            })
+            .filter(|item| match &self.tag {
+                Some(tag) => item.tags.contains(tag),
+                None => true,
+            })
+            .filter(|item| match self.max_duration {
+                Some(max) => item.duration[1] <= max,
+                None => true,
+            })
+            .filter(|item| match &keyword {
+                Some(keyword) => [item.name, item.abbreviation, item.description]
+                    .iter()
+                    .any(|text| text.to_lowercase().contains(keyword)),
+                None => true,
+            })
+            .collect();
+
+        match self.sort {
+            Some(ScaleSort::Popularity) => {
+                items.sort_by_cached_key(|item| std::cmp::Reverse(completions(item.id)))
+            }
+            Some(ScaleSort::Duration) => items.sort_by_key(|item| item.duration),
+            Some(ScaleSort::Name) => items.sort_by_key(|item| item.name),
+            None => {}
+        }
+
+        items
+    }
+}
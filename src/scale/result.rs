@@ -0,0 +1,561 @@
+use std::collections::BTreeMap;
+
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::error::{MindPulseError, MindPulseResult};
+
+/// 问卷中保留的题目字段，其余字段（维度、因子等）属于计分规则
+const QUESTION_FIELDS: &[&str] = &["title", "is_multiple", "options"];
+
+/// 单道题的计分规则
+struct QuestionKey {
+    /// 各选项的分值
+    points: Vec<i64>,
+    is_multiple: bool,
+    /// 题目所属的维度、因子等
+    attributes: Map<String, Value>,
+}
+
+/// 原始分的换算规则，对应量表数据中的 `formula_mode`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Formula {
+    multiply: f64,
+    round: bool,
+}
+
+impl Formula {
+    /// 解析 `{"operational_rule": {"multiply": 1.25}, "integer": "ROUND"}`，无法识别时不换算
+    fn parse(formula_mode: &Value) -> Option<Self> {
+        let multiply = formula_mode
+            .get("operational_rule")?
+            .get("multiply")?
+            .as_f64()?;
+        let round = formula_mode.get("integer").and_then(Value::as_str) == Some("ROUND");
+
+        Some(Self { multiply, round })
+    }
+
+    fn apply(self, total: i64) -> f64 {
+        let score = total as f64 * self.multiply;
+        if self.round {
+            score.round()
+        } else {
+            score
+        }
+    }
+}
+
+/// 量表的计分规则，启动时从完整数据中拆出
+pub struct ScoringKey {
+    questions: Vec<QuestionKey>,
+    interpretation: Value,
+    formula: Option<Formula>,
+}
+
+impl ScoringKey {
+    /// 将量表的完整数据拆分为问卷和计分规则
+    pub fn split(mut scale: Value) -> (Value, Self) {
+        let mut key = Self {
+            questions: Vec::new(),
+            interpretation: Value::Null,
+            formula: None,
+        };
+
+        let Some(object) = scale.as_object_mut() else {
+            return (scale, key);
+        };
+
+        // 解释和计算表达式只在服务端使用
+        key.interpretation = object.remove("interpretation").unwrap_or_default();
+        key.formula = object
+            .remove("formula_mode")
+            .as_ref()
+            .and_then(Formula::parse);
+
+        if let Some(Value::Array(questions)) = object.get_mut("questions") {
+            key.questions = questions.iter_mut().map(split_question).collect();
+        }
+
+        (scale, key)
+    }
+
+    /// 根据答案计分，返回总分、换算后的得分、各维度得分和对应的结果解释
+    ///
+    /// 不返回每道题的得分，否则多次提交即可还原出全部选项分值。
+    pub fn score(&'static self, id: u16, answers: &[Answer]) -> MindPulseResult<ScaleResult> {
+        if answers.len() != self.questions.len() {
+            return Err(MindPulseError::InvalidAnswers(format!(
+                "需要 {} 个答案，实际提交了 {} 个",
+                self.questions.len(),
+                answers.len()
+            )));
+        }
+
+        let points = self
+            .questions
+            .iter()
+            .zip(answers)
+            .enumerate()
+            .map(|(index, (question, answer))| question.score(index, answer))
+            .collect::<MindPulseResult<Vec<_>>>()?;
+
+        let total = points.iter().sum();
+        let score = match self.formula {
+            Some(formula) => formula.apply(total),
+            None => total as f64,
+        };
+
+        let mut dimensions: BTreeMap<&'static str, BTreeMap<&'static str, DimensionScore>> =
+            BTreeMap::new();
+        for (question, point) in self.questions.iter().zip(&points) {
+            for (attribute, value) in &question.attributes {
+                if let Some(value) = value.as_str() {
+                    dimensions
+                        .entry(attribute.as_str())
+                        .or_default()
+                        .entry(value)
+                        .or_default()
+                        .add(*point);
+                }
+            }
+        }
+
+        let interpretation = self.interpret(score, &points, &dimensions);
+
+        Ok(ScaleResult {
+            id,
+            total,
+            score,
+            dimensions,
+            interpretation,
+        })
+    }
+
+    /// 按得分选出对应的结果解释
+    ///
+    /// 按分数段划分的量表返回命中的一段；症状自评量表按阳性标准返回阳性的因子；
+    /// 其他量表的常模与性别、年龄有关，返回完整的解释，由客户端按维度得分查表。
+    fn interpret(
+        &'static self,
+        score: f64,
+        points: &[i64],
+        dimensions: &BTreeMap<&'static str, BTreeMap<&'static str, DimensionScore>>,
+    ) -> Option<Value> {
+        match &self.interpretation {
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .find(|(index, item)| {
+                    // 范围左开右闭，第一段包含下限
+                    score_range(item).is_some_and(|(low, high)| {
+                        (score > low || (*index == 0 && score >= low)) && score <= high
+                    })
+                })
+                .map(|(_, item)| item.clone()),
+            Value::Object(object) if object.contains_key("positive") => {
+                symptom_interpretation(object, score, points, dimensions.get("symptom"))
+            }
+            Value::Null => None,
+            interpretation => Some(interpretation.clone()),
+        }
+    }
+}
+
+/// 解释条目的分数段，`range` 为 `[下限, 上限]` 或 `{"total": [下限, 上限], ...}`
+fn score_range(item: &Value) -> Option<(f64, f64)> {
+    let range = item.get("range")?;
+    let range = range.get("total").unwrap_or(range);
+
+    Some((range.get(0)?.as_f64()?, range.get(1)?.as_f64()?))
+}
+
+/// 症状自评量表：总分、阳性项目数或任一因子均分超过标准即为阳性，同时返回均分超标的因子
+fn symptom_interpretation(
+    interpretation: &Map<String, Value>,
+    score: f64,
+    points: &[i64],
+    symptoms: Option<&BTreeMap<&'static str, DimensionScore>>,
+) -> Option<Value> {
+    let positive = interpretation.get("positive")?;
+    let rule = |name: &str| {
+        let rule = positive.get(name)?;
+        let value = rule.get("value")?.as_f64()?;
+        let operator = rule.get("comparison_operator")?.as_str()?;
+        Some(move |actual: f64| compare(actual, operator, value))
+    };
+
+    let total = rule("total")?;
+    let amount = rule("positive_amount")?;
+    let average = rule("any_symptom_average")?;
+
+    // 选择“从无”以外的选项（2 分及以上）为阳性项目
+    let positive_amount = points.iter().filter(|p| **p >= 2).count() as f64;
+
+    let factors: Vec<Value> = symptoms
+        .into_iter()
+        .flatten()
+        .filter(|(_, dimension)| average(dimension.average))
+        .filter_map(|(symptom, dimension)| {
+            let detail = interpretation.get("symptoms")?.get(*symptom)?;
+            Some(json!({
+                "symptom": symptom,
+                "average": dimension.average,
+                "interpretation": detail,
+            }))
+        })
+        .collect();
+
+    Some(json!({
+        "positive": total(score) || amount(positive_amount) || !factors.is_empty(),
+        "positive_amount": positive_amount,
+        "symptoms": factors,
+    }))
+}
+
+fn compare(actual: f64, operator: &str, value: f64) -> bool {
+    match operator {
+        ">" => actual > value,
+        ">=" => actual >= value,
+        "<" => actual < value,
+        "<=" => actual <= value,
+        _ => actual == value,
+    }
+}
+
+/// 去掉题目中的计分字段和选项分值
+fn split_question(question: &mut Value) -> QuestionKey {
+    let mut key = QuestionKey {
+        points: Vec::new(),
+        is_multiple: false,
+        attributes: Map::new(),
+    };
+
+    let Some(object) = question.as_object_mut() else {
+        return key;
+    };
+
+    key.is_multiple = object
+        .get("is_multiple")
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+
+    let fields: Vec<String> = object
+        .keys()
+        .filter(|k| !QUESTION_FIELDS.contains(&k.as_str()))
+        .cloned()
+        .collect();
+    for field in fields {
+        if let Some(value) = object.remove(&field) {
+            key.attributes.insert(field, value);
+        }
+    }
+
+    if let Some(Value::Array(options)) = object.get_mut("options") {
+        key.points = options
+            .iter_mut()
+            .map(|option| {
+                option
+                    .as_object_mut()
+                    .and_then(|o| o.remove("point"))
+                    .and_then(|p| p.as_i64())
+                    .unwrap_or_default()
+            })
+            .collect();
+    }
+
+    key
+}
+
+impl QuestionKey {
+    /// 所选选项的分值之和
+    fn score(&self, index: usize, answer: &Answer) -> MindPulseResult<i64> {
+        let selected = match answer {
+            Answer::Single(option) => std::slice::from_ref(option),
+            Answer::Multiple(options) if self.is_multiple => options.as_slice(),
+            Answer::Multiple(_) => {
+                return Err(MindPulseError::InvalidAnswers(format!(
+                    "第 {} 题为单选题",
+                    index + 1
+                )))
+            }
+        };
+
+        // 多选题的每个选项只能选一次，否则重复提交同一选项可以任意抬高总分
+        if selected.len() > self.points.len() {
+            return Err(MindPulseError::InvalidAnswers(format!(
+                "第 {} 题最多选择 {} 个选项",
+                index + 1,
+                self.points.len()
+            )));
+        }
+        let mut seen = vec![false; self.points.len()];
+
+        selected
+            .iter()
+            .map(|option| {
+                let point = self.points.get(*option).copied().ok_or_else(|| {
+                    MindPulseError::InvalidAnswers(format!(
+                        "第 {} 题没有第 {} 个选项",
+                        index + 1,
+                        option + 1
+                    ))
+                })?;
+
+                if std::mem::replace(&mut seen[*option], true) {
+                    return Err(MindPulseError::InvalidAnswers(format!(
+                        "第 {} 题重复选择了第 {} 个选项",
+                        index + 1,
+                        option + 1
+                    )));
+                }
+
+                Ok(point)
+            })
+            .sum()
+    }
+}
+
+/// 一道题的答案，值为选项下标（从 0 开始）
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum Answer {
+    /// 单选题
+    Single(usize),
+    /// 多选题
+    Multiple(Vec<usize>),
+}
+
+/// 提交的答案
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScaleAnswers {
+    /// 按题目顺序排列的答案
+    pub answers: Vec<Answer>,
+}
+
+/// 一个维度的得分
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct DimensionScore {
+    /// 该维度所有题目的得分之和
+    total: i64,
+    /// 题目数
+    count: usize,
+    /// 平均分
+    average: f64,
+}
+
+impl DimensionScore {
+    fn add(&mut self, point: i64) {
+        self.total += point;
+        self.count += 1;
+        self.average = self.total as f64 / self.count as f64;
+    }
+}
+
+/// 计分结果
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScaleResult {
+    /// 量表 ID
+    id: u16,
+    /// 原始分，所有题目的得分之和
+    total: i64,
+    /// 按量表的计算规则换算后的得分，没有规则时等于原始分
+    score: f64,
+    /// 各维度的得分，按题目的字段（如 `dimension`、`symptom`）和取值分组
+    #[salvo(schema(value_type = Object))]
+    dimensions: BTreeMap<&'static str, BTreeMap<&'static str, DimensionScore>>,
+    /// 与得分对应的结果解释
+    #[salvo(schema(value_type = Object))]
+    interpretation: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::scale::{serialize_scale_by_id, LIST};
+
+    /// 抑郁自评量表
+    const SDS: u16 = 7;
+    /// 症状自评量表
+    const SCL_90: u16 = 5;
+    /// 霍兰德职业兴趣测评，含多选题
+    const HOLLAND: u16 = 0;
+
+    fn split(id: u16) -> (Value, ScoringKey) {
+        assert!(LIST.iter().any(|p| p.id == id));
+        let scale = serde_json::from_slice(&serialize_scale_by_id(id).unwrap()).unwrap();
+        ScoringKey::split(scale)
+    }
+
+    fn key(id: u16) -> &'static ScoringKey {
+        Box::leak(Box::new(split(id).1))
+    }
+
+    /// 每道题选择分值为 `points[i]` 的选项
+    fn answers_with_points(key: &ScoringKey, points: impl Fn(usize) -> i64) -> Vec<Answer> {
+        key.questions
+            .iter()
+            .enumerate()
+            .map(|(index, question)| {
+                let point = points(index);
+                Answer::Single(question.points.iter().position(|p| *p == point).unwrap())
+            })
+            .collect()
+    }
+
+    fn score(id: u16, answers: Value) -> MindPulseResult<ScaleResult> {
+        let answers: Vec<Answer> = serde_json::from_value(answers).unwrap();
+        key(id).score(id, &answers)
+    }
+
+    fn assert_invalid(result: MindPulseResult<ScaleResult>) {
+        assert!(matches!(result, Err(MindPulseError::InvalidAnswers(_))));
+    }
+
+    /// 霍兰德测评的多选题下标及其选项数，其余题目选第一项
+    fn holland_answers(multiple: Value) -> Value {
+        let key = key(HOLLAND);
+        let index = key.questions.iter().position(|q| q.is_multiple).unwrap();
+
+        let mut answers = vec![json!(0); key.questions.len()];
+        answers[index] = multiple;
+        Value::Array(answers)
+    }
+
+    #[test]
+    fn split_removes_scoring_fields() {
+        let (questionnaire, key) = split(SDS);
+
+        assert!(questionnaire.get("interpretation").is_none());
+        assert!(questionnaire.get("formula_mode").is_none());
+        assert!(!questionnaire.to_string().contains("\"point\""));
+        assert_eq!(questionnaire["questions"].as_array().unwrap().len(), 20);
+
+        assert_eq!(key.questions.len(), 20);
+        assert_eq!(key.questions[0].points, [1, 2, 3, 4]);
+        // 反向计分题
+        assert_eq!(key.questions[1].points, [4, 3, 2, 1]);
+        assert_eq!(
+            key.formula,
+            Some(Formula {
+                multiply: 1.25,
+                round: true
+            })
+        );
+    }
+
+    #[test]
+    fn split_moves_dimensions_into_key() {
+        let (questionnaire, key) = split(SCL_90);
+
+        assert!(questionnaire["questions"][0].get("symptom").is_none());
+        assert_eq!(key.questions[0].attributes["symptom"], "SOMATIZATION");
+    }
+
+    #[test]
+    fn sds_score_is_converted_before_interpretation() {
+        let key = key(SDS);
+
+        // 原始分 50 落在正常范围内，换算后 62.5 四舍五入为 63，属于轻度
+        let answers = answers_with_points(key, |i| if i < 10 { 3 } else { 2 });
+        let result = key.score(SDS, &answers).unwrap();
+        assert_eq!(result.total, 50);
+        assert_eq!(result.score, 63.0);
+        assert_eq!(
+            result.interpretation.unwrap()["description"],
+            "轻度抑郁倾向"
+        );
+
+        let result = key.score(SDS, &answers_with_points(key, |_| 1)).unwrap();
+        assert_eq!(result.score, 25.0);
+        assert_eq!(result.interpretation.unwrap()["description"], "正常范围");
+
+        let result = key.score(SDS, &answers_with_points(key, |_| 4)).unwrap();
+        assert_eq!(result.score, 100.0);
+        assert_eq!(result.interpretation.unwrap()["description"], "重度抑郁");
+    }
+
+    #[test]
+    fn result_does_not_reveal_option_points() {
+        let key = key(SDS);
+        let result = key.score(SDS, &answers_with_points(key, |_| 2)).unwrap();
+        let result = serde_json::to_value(result).unwrap();
+
+        assert!(result.get("questions").is_none());
+        assert!(result.get("formula_mode").is_none());
+        // 只返回命中的一段解释
+        assert!(result["interpretation"].is_object());
+    }
+
+    #[test]
+    fn scl_90_reports_dimensions_and_positive_symptoms() {
+        let key = key(SCL_90);
+
+        let result = key.score(SCL_90, &answers_with_points(key, |_| 1)).unwrap();
+        assert_eq!(result.total, 90);
+        let somatization = &result.dimensions["symptom"]["SOMATIZATION"];
+        assert_eq!(somatization.count, 12);
+        assert_eq!(somatization.average, 1.0);
+        let interpretation = result.interpretation.unwrap();
+        assert_eq!(interpretation["positive"], false);
+        assert_eq!(interpretation["symptoms"], json!([]));
+
+        // 只有躯体化因子均分超过 2
+        let answers = answers_with_points(key, |i| {
+            if key.questions[i].attributes["symptom"] == "SOMATIZATION" {
+                3
+            } else {
+                1
+            }
+        });
+        let result = key.score(SCL_90, &answers).unwrap();
+        assert_eq!(result.dimensions["symptom"]["SOMATIZATION"].average, 3.0);
+        let interpretation = result.interpretation.unwrap();
+        assert_eq!(interpretation["positive"], true);
+        assert_eq!(interpretation["symptoms"].as_array().unwrap().len(), 1);
+        assert_eq!(interpretation["symptoms"][0]["symptom"], "SOMATIZATION");
+        assert_eq!(
+            interpretation["symptoms"][0]["interpretation"]["name"],
+            "躯体化"
+        );
+    }
+
+    #[test]
+    fn single_answer_on_multiple_choice_question() {
+        assert!(score(HOLLAND, holland_answers(json!(0))).is_ok());
+        assert!(score(HOLLAND, holland_answers(json!([0, 1]))).is_ok());
+    }
+
+    #[test]
+    fn multiple_answers_on_single_choice_question() {
+        let mut answers = vec![json!(0); 20];
+        answers[0] = json!([0, 1]);
+        assert_invalid(score(SDS, Value::Array(answers)));
+    }
+
+    #[test]
+    fn duplicate_options_are_rejected() {
+        assert_invalid(score(HOLLAND, holland_answers(json!([1, 1]))));
+        assert_invalid(score(HOLLAND, holland_answers(json!([1, 0, 1]))));
+    }
+
+    #[test]
+    fn too_many_selections_are_rejected() {
+        assert_invalid(score(HOLLAND, holland_answers(json!(vec![0; 50]))));
+    }
+
+    #[test]
+    fn out_of_range_option_is_rejected() {
+        assert_invalid(score(SDS, json!(vec![4; 20])));
+        assert_invalid(score(HOLLAND, holland_answers(json!([0, 99]))));
+    }
+
+    #[test]
+    fn wrong_answer_count_is_rejected() {
+        assert_invalid(score(SDS, json!(vec![0; 19])));
+        assert_invalid(score(SDS, json!(vec![0; 21])));
+    }
+}
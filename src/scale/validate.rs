@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use super::{serialize_scale_by_id, LIST};
+
+/// 检查所有量表的数据是否完整，返回发现的问题，为空时表示全部通过
+///
+/// 只检查编译期无法保证的内容：ID 唯一、题目和选项非空、每个选项都有整数分值、存在结果解释。
+pub fn validate_scales() -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut ids = HashSet::with_capacity(LIST.len());
+
+    for p in LIST {
+        let label = format!("#{} {}", p.id, p.slug);
+
+        if !ids.insert(p.id) {
+            problems.push(format!("{}：ID 重复", label));
+        }
+
+        let scale = match serialize_scale_by_id(p.id)
+            .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).map_err(Into::into))
+        {
+            Ok(scale) => scale,
+            Err(e) => {
+                problems.push(format!("{}：无法序列化：{}", label, e));
+                continue;
+            }
+        };
+
+        if p.name.trim().is_empty() {
+            problems.push(format!("{}：名称为空", label));
+        }
+
+        if scale.get("interpretation").is_none_or(Value::is_null) {
+            problems.push(format!("{}：缺少结果解释", label));
+        }
+
+        let questions = match scale.get("questions").and_then(Value::as_array) {
+            Some(questions) if !questions.is_empty() => questions,
+            _ => {
+                problems.push(format!("{}：没有题目", label));
+                continue;
+            }
+        };
+
+        for (index, question) in questions.iter().enumerate() {
+            let number = index + 1;
+
+            if question
+                .get("title")
+                .and_then(Value::as_str)
+                .is_none_or(|t| t.trim().is_empty())
+            {
+                problems.push(format!("{}：第 {} 题没有标题", label, number));
+            }
+
+            let options = match question.get("options").and_then(Value::as_array) {
+                Some(options) if !options.is_empty() => options,
+                _ => {
+                    problems.push(format!("{}：第 {} 题没有选项", label, number));
+                    continue;
+                }
+            };
+
+            for (option_index, option) in options.iter().enumerate() {
+                if option
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .is_none_or(|t| t.trim().is_empty())
+                {
+                    problems.push(format!(
+                        "{}：第 {} 题第 {} 个选项没有内容",
+                        label,
+                        number,
+                        option_index + 1
+                    ));
+                }
+
+                if option.get("point").and_then(Value::as_i64).is_none() {
+                    problems.push(format!(
+                        "{}：第 {} 题第 {} 个选项没有分值",
+                        label,
+                        number,
+                        option_index + 1
+                    ));
+                }
+            }
+        }
+    }
+
+    problems
+}
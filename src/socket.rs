@@ -0,0 +1,78 @@
+use std::{
+    fs::Permissions,
+    io::ErrorKind,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::Path,
+};
+
+use salvo::conn::unix::UnixAcceptor;
+use salvo::conn::UnixListener;
+use salvo::prelude::*;
+
+use crate::config::SocketMode;
+use crate::error::{MindPulseError, MindPulseResult};
+
+/// 监听 Unix 套接字，并按配置设置文件权限
+///
+/// 路径上残留的套接字文件（上次未正常退出）会先删除；仍有进程在监听时返回错误。
+pub async fn bind(path: &Path, mode: SocketMode) -> MindPulseResult<UnixAcceptor> {
+    remove_stale(path)?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            MindPulseError::Config(format!("无法创建套接字目录 {}：{}", parent.display(), e))
+        })?;
+    }
+
+    UnixListener::new(path.to_path_buf())
+        .permissions(Permissions::from_mode(mode.0))
+        .try_bind()
+        .await
+        .map_err(|e| {
+            error!(message = "Failed to bind Unix socket", path = %path.display(), error = ?e);
+            MindPulseError::Config(format!("无法监听套接字 {}：{}", path.display(), e))
+        })
+}
+
+/// 删除残留的套接字文件，路径不是套接字或仍在使用时不做改动
+fn remove_stale(path: &Path) -> MindPulseResult<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Err(MindPulseError::Config(format!(
+            "{} 已存在且不是套接字，拒绝覆盖",
+            path.display()
+        )));
+    }
+
+    // 能连上说明另一个进程正在监听
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(MindPulseError::Config(format!(
+            "套接字 {} 正在被其他进程使用",
+            path.display()
+        ))),
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+            warn!(message = "Removing stale socket", path = %path.display());
+            std::fs::remove_file(path)?;
+            Ok(())
+        }
+        Err(e) => Err(MindPulseError::Config(format!(
+            "无法检查套接字 {}：{}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// 服务停止后删除套接字文件
+pub fn remove(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => debug!(message = "Socket removed", path = %path.display()),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => warn!(message = "Failed to remove socket", path = %path.display(), error = ?e),
+    }
+}
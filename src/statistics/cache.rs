@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{error::MindPulseResult, scale::LIST};
+
+use super::{store::SharedStatisticsStore, ScaleStatistics};
+
+/// 与数据库对账的间隔
+pub const RECONCILE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 内存中的量表完成次数计数器
+///
+/// 键集合在启动时由 `LIST` 确定，之后只更新计数，读取无需加锁。
+pub struct StatisticsCache {
+    counts: HashMap<u16, AtomicU64>,
+}
+
+impl StatisticsCache {
+    /// 从存储后端加载统计数据
+    pub async fn load(store: SharedStatisticsStore) -> MindPulseResult<Arc<Self>> {
+        trace!(message = "Loading statistics cache");
+
+        let rows = store.query_statistics_counts().await?;
+
+        let counts = LIST.iter().map(|p| (p.id(), AtomicU64::new(0))).collect();
+        let cache = Arc::new(Self { counts });
+        cache.store(&rows);
+
+        info!(message = "Statistics cache loaded", scales = rows.len());
+
+        Ok(cache)
+    }
+
+    /// 用数据库中的统计结果覆盖内存计数
+    fn store(&self, rows: &[(u16, u64)]) {
+        // 数据库中没有记录的量表需要归零
+        for counter in self.counts.values() {
+            counter.store(0, Ordering::Relaxed);
+        }
+
+        // scale_index 为旧版量表的索引，不在 LIST 中的记录直接忽略
+        for (id, count) in rows {
+            if let Some(counter) = self.counts.get(id) {
+                counter.store(*count, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 用数据库中的真实数据校正内存计数
+    ///
+    /// 只由写入任务调用，与批量写入串行执行，对账期间不会有新的记录落盘或计数。
+    pub async fn reconcile(&self, store: &SharedStatisticsStore) -> MindPulseResult<()> {
+        trace!(message = "Reconciling statistics cache");
+
+        let rows = store.query_statistics_counts().await?;
+        self.store(&rows);
+
+        debug!(message = "Statistics cache reconciled", scales = rows.len());
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: u16) -> u64 {
+        self.counts
+            .get(&id)
+            .map(|c| c.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    /// 记录一次完成的测试
+    pub fn increment(&self, id: u16) {
+        if let Some(counter) = self.counts.get(&id) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 读取单个量表的统计数据
+    pub fn scale_statistics(&self, id: u16, name: &'static str) -> ScaleStatistics {
+        ScaleStatistics {
+            name,
+            count: self.get(id),
+        }
+    }
+
+    /// 读取所有量表的统计数据
+    pub fn all_statistics(&self) -> HashMap<u16, ScaleStatistics> {
+        LIST.iter()
+            .map(|p| {
+                (
+                    p.id(),
+                    ScaleStatistics {
+                        name: p.name(),
+                        count: self.get(p.id()),
+                    },
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use std::{future::Future, path::Path, time::Instant};
+
+use salvo::async_trait;
+use tracing::{Instrument, Span};
+
+use crate::error::MindPulseResult;
+use crate::metrics::metrics;
+
+use super::store::{CompletedTest, PoolState, SharedStatisticsStore, StatisticsStore};
+
+/// 记录每次数据库操作耗时的存储后端包装
+pub struct InstrumentedStore {
+    inner: SharedStatisticsStore,
+}
+
+impl InstrumentedStore {
+    pub fn new(inner: SharedStatisticsStore) -> Self {
+        Self { inner }
+    }
+
+    /// 记录耗时，启用链路导出时在 span 中执行
+    async fn observe<T>(
+        &self,
+        operation: &'static str,
+        future: impl Future<Output = MindPulseResult<T>>,
+    ) -> MindPulseResult<T> {
+        let span = db_span(self.inner.backend(), operation);
+
+        let started = Instant::now();
+        let result = future.instrument(span.clone()).await;
+        metrics().observe_db(operation, started.elapsed(), result.is_ok());
+
+        if result.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+
+        result
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+fn db_span(_backend: &'static str, _operation: &'static str) -> Span {
+    Span::none()
+}
+
+/// 导出链路时为每次数据库操作创建客户端 span
+#[cfg(feature = "otel")]
+fn db_span(backend: &'static str, operation: &'static str) -> Span {
+    info_span!(
+        "db",
+        otel.name = format!("{} {}", operation, backend),
+        otel.kind = "client",
+        otel.status_code = tracing::field::Empty,
+        db.system.name = backend,
+        db.operation.name = operation
+    )
+}
+
+#[async_trait]
+impl StatisticsStore for InstrumentedStore {
+    fn backend(&self) -> &'static str {
+        self.inner.backend()
+    }
+
+    async fn create_statistics_table(&self) -> MindPulseResult<()> {
+        self.observe("create_table", self.inner.create_statistics_table())
+            .await
+    }
+
+    async fn query_statistics_counts(&self) -> MindPulseResult<Vec<(u16, u64)>> {
+        self.observe("query_counts", self.inner.query_statistics_counts())
+            .await
+    }
+
+    async fn insert_completed_tests(&self, records: &[CompletedTest]) -> MindPulseResult<u64> {
+        self.observe("insert", self.inner.insert_completed_tests(records))
+            .await
+    }
+
+    fn pool_state(&self) -> Option<PoolState> {
+        self.inner.pool_state()
+    }
+
+    async fn ping(&self) -> MindPulseResult<()> {
+        self.observe("ping", self.inner.ping()).await
+    }
+
+    async fn backup(&self, dest: &Path) -> MindPulseResult<()> {
+        self.observe("backup", self.inner.backup(dest)).await
+    }
+
+    async fn close(&self) {
+        self.inner.close().await
+    }
+}
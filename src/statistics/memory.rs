@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use salvo::async_trait;
+
+use crate::error::{MindPulseError, MindPulseResult};
+
+use super::store::{CompletedTest, StatisticsStore};
+
+/// 内存存储，只保存每个量表的完成次数，供测试使用
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    counts: Mutex<HashMap<u16, u64>>,
+    /// 接下来需要失败的写入次数
+    failures: AtomicU32,
+}
+
+impl MemoryStore {
+    /// 前 `failures` 次写入返回错误的存储
+    pub fn failing(failures: u32) -> Self {
+        Self {
+            failures: AtomicU32::new(failures),
+            ..Default::default()
+        }
+    }
+
+    /// 已写入的完成次数
+    pub fn count(&self, id: u16) -> u64 {
+        self.lock()
+            .map(|counts| counts.get(&id).copied().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> MindPulseResult<std::sync::MutexGuard<'_, HashMap<u16, u64>>> {
+        self.counts.lock().map_err(|e| {
+            error!(message = "Memory store lock poisoned", error = ?e);
+            MindPulseError::Unavailable("内存存储不可用")
+        })
+    }
+}
+
+#[async_trait]
+impl StatisticsStore for MemoryStore {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn create_statistics_table(&self) -> MindPulseResult<()> {
+        Ok(())
+    }
+
+    async fn query_statistics_counts(&self) -> MindPulseResult<Vec<(u16, u64)>> {
+        Ok(self
+            .lock()?
+            .iter()
+            .map(|(id, count)| (*id, *count))
+            .collect())
+    }
+
+    async fn insert_completed_tests(&self, records: &[CompletedTest]) -> MindPulseResult<u64> {
+        let failing = self
+            .failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            return Err(MindPulseError::Unavailable("模拟的写入失败"));
+        }
+
+        let mut counts = self.lock()?;
+        for record in records {
+            *counts.entry(record.id).or_default() += 1;
+        }
+
+        Ok(records.len() as u64)
+    }
+}
@@ -1,83 +1,439 @@
+mod cache;
+mod instrumented;
+#[cfg(test)]
+mod memory;
+#[cfg(feature = "postgres")]
+mod postgres;
 mod sqlite;
+mod store;
+mod writer;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use salvo::{
-    handler,
-    oapi::extract::{HeaderParam, QueryParam},
+    http::StatusCode,
+    oapi::{endpoint, extract::QueryParam, ToSchema},
     writing::Json,
-    Response, Writer,
+    Depot, Request, Response, Writer,
 };
+use serde::{Deserialize, Serialize};
+use time::UtcOffset;
 
 use crate::{
+    client::client_address,
     error::{MindPulseError, MindPulseResult},
+    extract::JsonBody,
+    rate_limit::take_deferred,
     scale::get_scale_name_by_id,
-    statistics::sqlite::ClientType,
 };
 
-use self::sqlite::{insert_completed_test, query_all_statistics, query_scale_statistics};
+use self::cache::StatisticsCache;
+use self::store::{ClientType, CompletedTest};
+use self::writer::CompletionWriter;
+
+pub use self::sqlite::{ExclusiveLock, SqliteStore, SCHEMA_VERSION};
+pub use self::store::{connect_statistics_store, AccessMode, PoolState, SharedStatisticsStore};
+
+/// 统计模块的运行时状态，通过 `Depot` 注入到处理器中
+pub struct Statistics {
+    store: SharedStatisticsStore,
+    cache: Arc<StatisticsCache>,
+    writer: Arc<CompletionWriter>,
+    /// 完成时间使用的时区
+    utc_offset: UtcOffset,
+}
+
+impl Statistics {
+    /// 创建数据表、加载统计缓存并启动后台写入任务
+    pub async fn init(
+        store: SharedStatisticsStore,
+        utc_offset: UtcOffset,
+    ) -> MindPulseResult<Arc<Self>> {
+        store.create_statistics_table().await?;
+
+        let cache = StatisticsCache::load(store.clone()).await?;
+        let writer = CompletionWriter::start(store.clone(), cache.clone());
+
+        Ok(Arc::new(Self {
+            store,
+            cache,
+            writer,
+            utc_offset,
+        }))
+    }
+
+    /// 量表的完成次数
+    pub fn completions(&self, id: u16) -> u64 {
+        self.cache.get(id)
+    }
+
+    /// 确认存储后端可用
+    pub async fn ping(&self) -> MindPulseResult<()> {
+        self.store.ping().await
+    }
+
+    /// 连接池状态
+    pub fn pool_state(&self) -> Option<PoolState> {
+        self.store.pool_state()
+    }
+
+    /// 写入队列中等待的记录数
+    pub fn queue_depth(&self) -> usize {
+        self.writer.queue_depth()
+    }
+
+    /// 存储后端
+    pub fn store(&self) -> &SharedStatisticsStore {
+        &self.store
+    }
+
+    /// 存储后端名称
+    pub fn backend(&self) -> &'static str {
+        self.store.backend()
+    }
+
+    /// 确保队列中尚未写入的完成记录落盘，然后关闭存储后端
+    ///
+    /// 写入失败时最多重试 `timeout`，之后丢弃剩余记录。
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.writer.shutdown(timeout).await;
+        self.store.close().await;
+    }
+}
+
+/// 从 `Depot` 中取出统计模块状态
+pub(crate) fn obtain_statistics(depot: &Depot) -> MindPulseResult<&Arc<Statistics>> {
+    depot.obtain::<Arc<Statistics>>().map_err(|_| {
+        error!(message = "Statistics state is missing from depot");
+        MindPulseError::Unavailable("统计服务不可用")
+    })
+}
+
+/// 量表统计数据结构
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScaleStatistics {
+    name: &'static str,
+    count: u64,
+}
 
-pub use self::sqlite::create_statistics_table;
+/// 完成测试的请求体
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CompletionRequest {
+    /// 量表 ID
+    id: u16,
+    /// 客户端类型：1 微信小程序，2 移动端浏览器
+    client_type: u8,
+}
 
 /// 获取查询统计信息的处理器
-#[handler]
+///
+/// 不指定 `id` 时返回所有量表的统计数据，键为量表 ID。
+#[endpoint(
+    tags("statistics"),
+    status_codes(200, 400, 404, 503),
+    parameters(("id", description = "量表 ID")),
+    responses(
+        (status_code = 200, description = "指定 `id` 时只返回该量表的 `ScaleStatistics`", body = HashMap<u16, ScaleStatistics>),
+    )
+)]
+pub async fn get_statistics(
+    id: QueryParam<u16, false>,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    render_statistics(id.into_inner(), depot, res)
+}
+
+/// 记录一次完成的测试
+///
+/// 记录由后台任务批量写入，返回 202 时尚未落盘。
+#[endpoint(
+    tags("statistics"),
+    status_codes(202, 400, 404, 422, 429, 503),
+    parameters(("X-Forwarded-For", Header, description = "反向代理转发的客户端地址")),
+    responses((status_code = 202, description = "已加入写入队列"))
+)]
+pub async fn create_completion(
+    body: JsonBody<CompletionRequest>,
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> MindPulseResult<()> {
+    let CompletionRequest { id, client_type } = body.into_inner();
+    record_completion(id, client_type, req, depot).await?;
+    res.status_code(StatusCode::ACCEPTED);
+
+    Ok(())
+}
+
+/// 获取查询统计信息的处理器（旧版）
+///
+/// 已弃用，请使用 `GET /api/v1/statistics`。
+#[endpoint(
+    tags("legacy"),
+    status_codes(200, 400, 404, 503),
+    parameters(("id", description = "量表 ID")),
+    responses(
+        (status_code = 200, description = "指定 `id` 时只返回该量表的 `ScaleStatistics`", body = HashMap<u16, ScaleStatistics>),
+    )
+)]
 pub async fn handle_get_statistics(
     id: QueryParam<u16, false>,
+    depot: &mut Depot,
     res: &mut Response,
 ) -> MindPulseResult<()> {
+    render_statistics(id.into_inner(), depot, res)
+}
+
+/// 处理插入测试记录的请求（旧版）
+///
+/// 已弃用，请使用 `POST /api/v1/completions`。
+#[endpoint(
+    tags("legacy"),
+    status_codes(200, 400, 404, 422, 429, 503),
+    parameters(
+        ("id", description = "量表 ID"),
+        ("client_type", description = "客户端类型：1 微信小程序，2 移动端浏览器"),
+        ("X-Forwarded-For", Header, description = "反向代理转发的客户端地址"),
+    )
+)]
+pub async fn handle_insert_record(
+    id: QueryParam<u16, true>,
+    client_type: QueryParam<u8, true>,
+    req: &mut Request,
+    depot: &mut Depot,
+) -> MindPulseResult<()> {
+    record_completion(*id, *client_type, req, depot).await
+}
+
+fn render_statistics(id: Option<u16>, depot: &Depot, res: &mut Response) -> MindPulseResult<()> {
     trace!(message = "Querying statistics data");
 
-    match id.into_inner() {
+    let statistics = obtain_statistics(depot)?;
+
+    match id {
         None => {
             debug!(message = "No scale specified, querying all records");
-            res.render(Json(query_all_statistics().await?));
+            res.render(Json(statistics.cache.all_statistics()));
         }
         Some(id) => {
             debug!(message = "Querying specified scale record", id);
-            res.render(Json(query_scale_statistics(id).await?));
+
+            // 验证 ID
+            let name = get_scale_name_by_id(id)?;
+            res.render(Json(statistics.cache.scale_statistics(id, name)));
         }
     };
 
     Ok(())
 }
 
-/// 处理插入测试记录的请求
-#[handler]
-pub async fn handle_insert_record(
-    id: QueryParam<u16, true>,
-    client_type: QueryParam<u8, true>,
-    x_forwarded_for: HeaderParam<&str, false>,
+async fn record_completion(
+    id: u16,
+    client_type: u8,
+    req: &Request,
+    depot: &mut Depot,
 ) -> MindPulseResult<()> {
-    trace!(
-        message = "Inserting test record",
-        id = *id,
-        client_type = *client_type
-    );
+    trace!(message = "Inserting test record", id, client_type);
 
     // 验证 ID
-    let name = get_scale_name_by_id(*id)?;
+    let name = get_scale_name_by_id(id)?;
     debug!(
         message = "Successfully resolved scale index",
         name = name,
-        id = *id
+        id = id
     );
 
     // 验证 client type
-    let client_type: ClientType = (*client_type).try_into().map_err(|e| {
+    let client_type: ClientType = client_type.try_into().map_err(|e| {
         error!(message = "Failed to convert client type", error = ?e);
-        MindPulseError::Response("无效的 clientType".to_owned())
+        e
     })?;
 
+    // 请求有效时才消耗限流令牌
+    take_deferred(req, depot)?;
+
     // 获取客户端 IP 地址
-    let client_ip = x_forwarded_for
-        .into_inner()
-        .unwrap_or("")
-        .split(',')
-        .next()
-        .unwrap_or("")
-        .trim();
+    let client_ip = client_address(req, depot);
     debug!(message = "Retrieved client IP address", ip = client_ip);
 
-    // 插入测试记录
-    insert_completed_test(*id, client_type, client_ip).await?;
+    // 放入写入队列，由后台任务批量写入
+    let statistics = obtain_statistics(depot)?;
+    statistics
+        .writer
+        .enqueue(CompletedTest::new(
+            id,
+            client_type,
+            &client_ip,
+            statistics.utc_offset,
+        ))
+        .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use salvo::{
+        affix_state,
+        test::{ResponseExt, TestClient},
+        Router, Service,
+    };
+    use serde_json::{json, Value};
+
+    use super::memory::MemoryStore;
+    use super::store::StatisticsStore;
+    use super::*;
+    use crate::rate_limit::{RateLimit, RateLimitRule, RateLimitStore};
+
+    /// 抑郁自评量表
+    const SDS: u16 = 7;
+
+    async fn service() -> (Arc<MemoryStore>, Arc<Statistics>, Service) {
+        let store = Arc::new(MemoryStore::default());
+        let statistics = Statistics::init(store.clone(), UtcOffset::UTC)
+            .await
+            .unwrap();
+
+        let router = Router::new()
+            .hoop(affix_state::inject(statistics.clone()))
+            .push(Router::with_path("completions").post(create_completion))
+            .push(Router::with_path("statistics").get(get_statistics))
+            .push(Router::with_path("legacy").get(handle_insert_record));
+
+        (store, statistics, Service::new(router))
+    }
+
+    async fn post_completion(service: &Service, body: Value) -> u16 {
+        TestClient::post("http://127.0.0.1/completions")
+            .json(&body)
+            .send(service)
+            .await
+            .status_code
+            .unwrap()
+            .as_u16()
+    }
+
+    #[tokio::test]
+    async fn completions_are_written_and_counted() {
+        let (store, statistics, service) = service().await;
+
+        for _ in 0..3 {
+            let status = post_completion(&service, json!({ "id": SDS, "client_type": 1 })).await;
+            assert_eq!(status, 202);
+        }
+        let status = TestClient::get(format!("http://127.0.0.1/legacy?id={}&client_type=2", SDS))
+            .send(&service)
+            .await
+            .status_code
+            .unwrap();
+        assert_eq!(status, StatusCode::OK);
+
+        statistics.shutdown(Duration::from_secs(1)).await;
+        assert_eq!(store.count(SDS), 4);
+        assert_eq!(statistics.completions(SDS), 4);
+
+        let body: Value = TestClient::get(format!("http://127.0.0.1/statistics?id={}", SDS))
+            .send(&service)
+            .await
+            .take_json()
+            .await
+            .unwrap();
+        assert_eq!(body["count"], 4);
+    }
+
+    #[tokio::test]
+    async fn invalid_completions_are_rejected() {
+        let (store, statistics, service) = service().await;
+
+        let status = post_completion(&service, json!({ "id": 999, "client_type": 1 })).await;
+        assert_eq!(status, 404);
+        let status = post_completion(&service, json!({ "id": SDS, "client_type": 9 })).await;
+        assert_eq!(status, 422);
+
+        // 字段缺失或类型不符时指明字段，语法错误仍为 400
+        for (body, status, field) in [
+            (r#"{ "id": 7 }"#, 422, Some("client_type")),
+            (r#"{ "id": "7", "client_type": 1 }"#, 422, Some("id")),
+            (r#"{ "id": 7, "client_type": 1"#, 400, None),
+        ] {
+            let mut res = TestClient::post("http://127.0.0.1/completions")
+                .raw_json(body)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap().as_u16(), status, "{}", body);
+            let problem: Value = res.take_json().await.unwrap();
+            assert_eq!(problem["field"].as_str(), field, "{}", body);
+        }
+
+        statistics.shutdown(Duration::from_secs(1)).await;
+        assert_eq!(store.count(SDS), 0);
+    }
+
+    #[tokio::test]
+    async fn invalid_completions_do_not_consume_rate_limit() {
+        let statistics = Statistics::init(Arc::new(MemoryStore::default()), UtcOffset::UTC)
+            .await
+            .unwrap();
+        let limits = RateLimitStore::init(&["completions"], None);
+        let rule = RateLimitRule::new(1, Duration::from_secs(60));
+
+        let router = Router::new()
+            .hoop(affix_state::inject(statistics.clone()).inject(limits))
+            .push(
+                Router::with_path("completions")
+                    .hoop(RateLimit::deferred("completions", rule))
+                    .post(create_completion),
+            );
+        let service = Service::new(router);
+
+        for body in [
+            json!({ "id": 999, "client_type": 1 }),
+            json!({ "id": SDS, "client_type": 9 }),
+            json!({ "id": SDS }),
+        ] {
+            assert_ne!(post_completion(&service, body).await, 429);
+        }
+
+        let valid = json!({ "id": SDS, "client_type": 1 });
+        assert_eq!(post_completion(&service, valid.clone()).await, 202);
+        assert_eq!(post_completion(&service, valid).await, 429);
+
+        statistics.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn cache_is_loaded_from_store() {
+        let store = Arc::new(MemoryStore::default());
+        let records: Vec<_> = (0..2)
+            .map(|_| CompletedTest::new(SDS, ClientType::Wechat, "127.0.0.1", UtcOffset::UTC))
+            .collect();
+        store.insert_completed_tests(&records).await.unwrap();
+
+        let statistics = Statistics::init(store.clone(), UtcOffset::UTC)
+            .await
+            .unwrap();
+        assert_eq!(statistics.completions(SDS), 2);
+        assert_eq!(statistics.cache.all_statistics()[&SDS].count, 2);
+        statistics.shutdown(Duration::from_secs(1)).await;
+    }
+
+    #[tokio::test]
+    async fn failing_store_recovers() {
+        let store = MemoryStore::failing(2);
+        let records = [CompletedTest::new(
+            SDS,
+            ClientType::MobileBrowser,
+            "127.0.0.1",
+            UtcOffset::UTC,
+        )];
+
+        assert!(store.insert_completed_tests(&records).await.is_err());
+        assert!(store.insert_completed_tests(&records).await.is_err());
+        assert_eq!(store.insert_completed_tests(&records).await.unwrap(), 1);
+        assert_eq!(
+            store.query_statistics_counts().await.unwrap(),
+            vec![(SDS, 1)]
+        );
+    }
+}
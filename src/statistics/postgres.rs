@@ -0,0 +1,153 @@
+use salvo::async_trait;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+use crate::error::MindPulseResult;
+
+use super::store::{CompletedTest, PoolState, StatisticsStore};
+
+/// PostgreSQL 存储后端
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str, max_connections: u32) -> MindPulseResult<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(url)
+            .await
+            .map_err(|e| {
+                error!(message = "Failed to create database connection pool", error = ?e);
+                e
+            })?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl StatisticsStore for PostgresStore {
+    fn backend(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn create_statistics_table(&self) -> MindPulseResult<()> {
+        trace!(message = "Creating statistics table");
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS statistics_ip (
+                id            BIGSERIAL PRIMARY KEY,
+                scale_index   INTEGER NOT NULL,
+                ip            VARCHAR(45) NOT NULL DEFAULT '',
+                client_type   SMALLINT NOT NULL,
+                finished_time TIMESTAMPTZ NOT NULL
+            )",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!(message = "Failed to create statistics table", error = ?e);
+            e
+        })?;
+
+        info!(
+            message = "Statistics table created or already exists",
+            table = "statistics_ip"
+        );
+
+        Ok(())
+    }
+
+    async fn query_statistics_counts(&self) -> MindPulseResult<Vec<(u16, u64)>> {
+        trace!(message = "Querying all scale statistics");
+        let rows: Vec<(i32, i64)> = sqlx::query_as(
+            "SELECT scale_index, COUNT(*) as count
+             FROM statistics_ip
+             GROUP BY scale_index",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!(message = "Failed to query all statistics", error = ?e);
+            e
+        })?;
+
+        debug!(message = "All scale statistics retrieved", rows = ?rows);
+
+        // PostgreSQL 没有无符号整数，取出后再转换
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, count)| Some((u16::try_from(id).ok()?, count as u64)))
+            .collect())
+    }
+
+    async fn insert_completed_tests(&self, records: &[CompletedTest]) -> MindPulseResult<u64> {
+        trace!(message = "Inserting test records", count = records.len());
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            error!(message = "Failed to begin transaction", error = ?e);
+            e
+        })?;
+
+        let mut rows_affected = 0;
+        for record in records {
+            let result = sqlx::query(
+                "INSERT INTO statistics_ip (scale_index, ip, finished_time, client_type) VALUES ($1, $2, $3, $4)",
+            )
+            .bind(i32::from(record.id))
+            .bind(&record.ip)
+            .bind(record.finished_time)
+            .bind(record.client_type as i16)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                error!(
+                    message = "Failed to insert test record",
+                    error = ?e,
+                    scale_index = record.id,
+                    client_type = ?record.client_type
+                );
+                e
+            })?;
+
+            rows_affected += result.rows_affected();
+        }
+
+        tx.commit().await.map_err(|e| {
+            error!(message = "Failed to commit test records", error = ?e);
+            e
+        })?;
+
+        info!(
+            message = "Test records inserted successfully",
+            count = rows_affected
+        );
+
+        Ok(rows_affected)
+    }
+
+    fn pool_state(&self) -> Option<PoolState> {
+        Some(PoolState {
+            size: self.pool.size(),
+            idle: self.pool.num_idle() as u32,
+            max: self.pool.options().get_max_connections(),
+        })
+    }
+
+    async fn ping(&self) -> MindPulseResult<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                warn!(message = "Database ping failed", error = ?e);
+                e
+            })?;
+
+        Ok(())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+        info!(message = "Database connection pool closed");
+    }
+}
@@ -1,227 +1,370 @@
-use std::{collections::HashMap, env};
+use std::{path::Path, str::FromStr, time::Duration};
+
+use salvo::async_trait;
+use sqlx::{
+    sqlite::{
+        SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqliteLockingMode,
+        SqlitePoolOptions, SqliteSynchronous,
+    },
+    ConnectOptions, Connection, Pool, Sqlite,
+};
 
-use serde::Serialize;
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
-use time::macros::offset;
-use tokio::sync::OnceCell;
+use crate::error::{MindPulseError, MindPulseResult};
 
-use crate::{
-    error::{MindPulseError, MindPulseResult},
-    scale::{get_scale_name_by_id, LIST},
-};
+use super::store::{AccessMode, CompletedTest, PoolState, StatisticsStore};
 
 type SqlitePool = Pool<Sqlite>;
 
-static SQLITE_POOL: OnceCell<SqlitePool> = OnceCell::const_new();
-
-/// 获取全局 SQLite 数据库连接池
-async fn get_database_pool() -> &'static SqlitePool {
-    let mind_pulse_db_path =
-        env::var("MIND_PULSE_DB_PATH").unwrap_or("./mind_pulse.sqlite".to_string());
-    info!(message = "Using database path", path = mind_pulse_db_path);
-
-    let db_url = format!("{}?mode=rwc", mind_pulse_db_path);
-
-    SQLITE_POOL
-        .get_or_init(|| async {
-            SqlitePoolOptions::new()
-                .max_connections(5)
-                .connect(&db_url)
-                .await
-                .map_err(|e| {
-                    error!(message = "Failed to create database connection pool", error = ?e);
-                    e
-                })
-                .unwrap()
-        })
-        .await
+/// 数据表结构的版本，保存在 `PRAGMA user_version` 中，恢复备份时据此校验
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// PRAGMA 不支持参数绑定，修改 `SCHEMA_VERSION` 时需要同时修改这里
+const SET_SCHEMA_VERSION: &str = "PRAGMA user_version = 1";
+
+/// SQLite 存储后端
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// 连接数据库，`path` 可以是文件路径或 `sqlite://` 开头的 URL
+    ///
+    /// 读写模式下数据库文件及其所在目录不存在时自动创建，使用 WAL 日志模式和
+    /// `synchronous=NORMAL`，写入完成记录时不阻塞读取；数据库被锁定时最多等待 `busy_timeout`。
+    /// 只读模式不创建任何文件，数据库不存在时返回错误，避免路径写错时得到一个空数据库。
+    pub async fn connect(
+        path: &str,
+        max_connections: u32,
+        busy_timeout: Duration,
+        mode: AccessMode,
+    ) -> MindPulseResult<Self> {
+        info!(
+            message = "Using database path",
+            path,
+            max_connections,
+            busy_timeout = ?busy_timeout,
+            mode = ?mode
+        );
+
+        let options = SqliteConnectOptions::from_str(path)
+            .map_err(|e| MindPulseError::Config(format!("无效的数据库地址 `{}`：{}", path, e)))?
+            .busy_timeout(busy_timeout);
+
+        let options = match mode {
+            AccessMode::ReadWrite => {
+                let options = options
+                    .create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .synchronous(SqliteSynchronous::Normal);
+                create_parent_dir(options.get_filename())?;
+                options
+            }
+            AccessMode::ReadOnly => {
+                let filename = options.get_filename();
+                if !is_memory(filename) && !filename.is_file() {
+                    return Err(MindPulseError::Config(format!(
+                        "数据库 {} 不存在",
+                        filename.display()
+                    )));
+                }
+                options.read_only(true)
+            }
+        };
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .map_err(|e| {
+                error!(message = "Failed to create database connection pool", error = ?e);
+                MindPulseError::Config(format!("无法打开数据库 {}：{}", path, e))
+            })?;
+
+        Ok(Self { pool })
+    }
+
+    /// 以只读方式检查数据库文件，返回其结构版本
+    ///
+    /// 文件损坏或缺少统计数据表时返回错误。
+    pub async fn inspect(path: &Path) -> MindPulseResult<i64> {
+        let options = SqliteConnectOptions::new().filename(path).read_only(true);
+
+        let mut conn = options.connect().await.map_err(|e| {
+            MindPulseError::Config(format!("无法打开数据库 {}：{}", path.display(), e))
+        })?;
+
+        // 不是 SQLite 文件时在第一次查询才会报错
+        let (integrity,): (String,) = sqlx::query_as("PRAGMA quick_check")
+            .fetch_one(&mut conn)
+            .await
+            .map_err(|e| {
+                MindPulseError::Config(format!("{} 不是有效的数据库：{}", path.display(), e))
+            })?;
+        if integrity != "ok" {
+            return Err(MindPulseError::Config(format!(
+                "数据库 {} 已损坏：{}",
+                path.display(),
+                integrity
+            )));
+        }
+
+        let (tables,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'statistics_ip'",
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        if tables == 0 {
+            return Err(MindPulseError::Config(format!(
+                "数据库 {} 中没有统计数据表",
+                path.display()
+            )));
+        }
+
+        let (version,): (i64,) = sqlx::query_as("PRAGMA user_version")
+            .fetch_one(&mut conn)
+            .await?;
+
+        conn.close().await?;
+
+        Ok(version)
+    }
 }
 
-/// 量表统计数据结构
-#[derive(Debug, Serialize)]
-pub struct ScaleStatistics<'a> {
-    name: &'a str,
-    count: u64,
+/// 数据库的排他锁，持有期间其他连接无法读写，关闭连接后释放
+pub struct ExclusiveLock(SqliteConnection);
+
+impl ExclusiveLock {
+    /// 取得数据库的排他锁，数据库仍被其他进程（如正在运行的服务）打开时立即返回错误
+    ///
+    /// 同时把 WAL 中的内容写回主文件，之后只需移动主文件即可得到完整的数据库。
+    pub async fn acquire(path: &Path) -> MindPulseResult<Self> {
+        let in_use = |e: sqlx::Error| {
+            MindPulseError::Config(format!(
+                "数据库 {} 正在被使用，请先停止服务：{}",
+                path.display(),
+                e
+            ))
+        };
+
+        // 不等待，服务持有的连接会让加锁立即失败
+        let mut conn = SqliteConnectOptions::new()
+            .filename(path)
+            .locking_mode(SqliteLockingMode::Exclusive)
+            .busy_timeout(Duration::ZERO)
+            .connect()
+            .await
+            .map_err(|e| {
+                MindPulseError::Config(format!("无法打开数据库 {}：{}", path.display(), e))
+            })?;
+
+        // 排他模式下提交后仍然持有锁，直到连接关闭
+        sqlx::query("BEGIN EXCLUSIVE")
+            .execute(&mut conn)
+            .await
+            .map_err(in_use)?;
+        sqlx::query("COMMIT")
+            .execute(&mut conn)
+            .await
+            .map_err(in_use)?;
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&mut conn)
+            .await
+            .map_err(in_use)?;
+
+        debug!(message = "Database exclusive lock acquired", path = %path.display());
+
+        Ok(Self(conn))
+    }
+
+    pub async fn release(self) -> MindPulseResult<()> {
+        self.0.close().await?;
+        Ok(())
+    }
 }
 
-/// 客户端类型枚举
-#[derive(sqlx::Type, Default, Debug, Clone, Copy)]
-#[repr(u8)]
-pub(crate) enum ClientType {
-    Wechat = 1,
-    #[default]
-    MobileBrowser,
+/// 内存数据库的文件名形如 `file:sqlx-in-memory-0`，不对应真实路径
+fn is_memory(filename: &Path) -> bool {
+    filename.to_string_lossy().starts_with("file:")
 }
 
-impl TryFrom<u8> for ClientType {
-    type Error = MindPulseError;
+/// 创建数据库文件所在的目录
+fn create_parent_dir(filename: &Path) -> MindPulseResult<()> {
+    if is_memory(filename) {
+        return Ok(());
+    }
 
-    fn try_from(value: u8) -> MindPulseResult<Self> {
-        match value {
-            1 => Ok(ClientType::Wechat),
-            2 => Ok(ClientType::MobileBrowser),
-            invalid => {
-                error!(message = "Invalid client type", value = invalid);
-                Err(MindPulseError::InvalidClientType(value))
-            }
+    match filename.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                error!(message = "Failed to create database directory", dir = %dir.display(), error = ?e);
+                MindPulseError::Config(format!("无法创建数据库目录 {}：{}", dir.display(), e))
+            })?;
+            info!(message = "Database directory created", dir = %dir.display());
+            Ok(())
         }
+        _ => Ok(()),
     }
 }
 
-/// 创建统计数据表
-pub async fn create_statistics_table() -> MindPulseResult<()> {
-    trace!(message = "Creating statistics table");
+#[async_trait]
+impl StatisticsStore for SqliteStore {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
 
-    let pool = get_database_pool().await;
+    async fn create_statistics_table(&self) -> MindPulseResult<()> {
+        trace!(message = "Creating statistics table");
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS statistics_ip (
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS statistics_ip (
                 id            INTEGER PRIMARY KEY,
                 scale_index   INTEGER NOT NULL,
                 ip            VARCHAR(15) NOT NULL DEFAULT '',
                 client_type   INTEGER NOT NULL,
                 finished_time DATETIME NOT NULL
             )",
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        error!(message = "Failed to create statistics table", error = ?e);
-        e
-    })?;
-
-    info!(
-        message = "Statistics table created or already exists",
-        table = "statistics_ip"
-    );
-
-    Ok(())
-}
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!(message = "Failed to create statistics table", error = ?e);
+            e
+        })?;
 
-/// 查询单个量表的统计数据
-pub async fn query_scale_statistics(id: u16) -> MindPulseResult<ScaleStatistics<'static>> {
-    trace!(message = "Querying scale statistics", id);
-
-    let pool = get_database_pool().await;
-
-    // 验证 ID
-    let name = get_scale_name_by_id(id)?;
-    debug!(
-        message = "Successfully resolved scale index",
-        name = name,
-        id = id
-    );
-
-    debug!(message = "Fetching test count", id);
-    let (count,): (u64,) =
-        sqlx::query_as("SELECT COUNT(*) as times FROM statistics_ip WHERE scale_index = $1")
-            .bind(id)
-            .fetch_one(pool)
+        sqlx::query(SET_SCHEMA_VERSION)
+            .execute(&self.pool)
             .await
             .map_err(|e| {
-                error!(message = "Failed to query scale statistics", id, error = ?e);
+                error!(message = "Failed to set schema version", error = ?e);
                 e
             })?;
 
-    info!(message = "Scale statistics retrieved", id, count);
+        info!(
+            message = "Statistics table created or already exists",
+            table = "statistics_ip",
+            schema_version = SCHEMA_VERSION
+        );
 
-    Ok(ScaleStatistics { name, count })
-}
+        Ok(())
+    }
+
+    async fn query_statistics_counts(&self) -> MindPulseResult<Vec<(u16, u64)>> {
+        // scale_index 为旧版量表的索引，现在已改为 id
+        trace!(message = "Querying all scale statistics");
+        let rows: Vec<(u16, u64)> = sqlx::query_as(
+            "SELECT scale_index, COUNT(*) as count
+             FROM statistics_ip
+             GROUP BY scale_index",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| {
+            error!(message = "Failed to query all statistics", error = ?e);
+            e
+        })?;
+
+        debug!(message = "All scale statistics retrieved", rows = ?rows);
+
+        Ok(rows)
+    }
 
-/// 查询所有量表的统计数据
-pub async fn query_all_statistics<'a>() -> MindPulseResult<HashMap<u16, ScaleStatistics<'a>>> {
-    let pool = get_database_pool().await;
-
-    // scale_index 为旧版量表的索引，现在已改为 id
-    trace!(message = "Querying all scale statistics");
-    let rows: Vec<(u16, u64)> = sqlx::query_as(
-        "SELECT scale_index, COUNT(*) as count
-         FROM statistics_ip
-         GROUP BY scale_index",
-    )
-    .fetch_all(pool)
-    .await
-    .map_err(|e| {
-        error!(message = "Failed to query all statistics", error = ?e);
-        e
-    })?;
-
-    // 构建完整的统计映射，包含未有任何记录的量表：HashMap<id, ScaleStatistics<'_>>
-    let mut statistics_map: HashMap<u16, ScaleStatistics<'_>> = LIST
-        .iter()
-        .map(|p| {
-            (
-                p.id(),
-                ScaleStatistics {
-                    name: p.name(),
-                    count: 0,
-                },
+    async fn insert_completed_tests(&self, records: &[CompletedTest]) -> MindPulseResult<u64> {
+        trace!(message = "Inserting test records", count = records.len());
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            error!(message = "Failed to begin transaction", error = ?e);
+            e
+        })?;
+
+        let mut rows_affected = 0;
+        for record in records {
+            // 为了兼容旧表，id 列名暂时仍使用 scale_index
+            let result = sqlx::query(
+                "INSERT INTO statistics_ip (scale_index, ip, finished_time, client_type) VALUES ($1, $2, $3, $4)",
             )
-        })
-        .collect();
+            .bind(record.id)
+            .bind(&record.ip)
+            .bind(record.finished_time)
+            .bind(record.client_type)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                error!(
+                    message = "Failed to insert test record",
+                    error = ?e,
+                    scale_index = record.id,
+                    client_type = ?record.client_type
+                );
+                e
+            })?;
 
-    // 更新实际统计数据
-    for (id, count) in rows.iter() {
-        if let Some(stats) = statistics_map.get_mut(id) {
-            stats.count = *count;
+            rows_affected += result.rows_affected();
         }
+
+        tx.commit().await.map_err(|e| {
+            error!(message = "Failed to commit test records", error = ?e);
+            e
+        })?;
+
+        info!(
+            message = "Test records inserted successfully",
+            count = rows_affected
+        );
+
+        Ok(rows_affected)
     }
 
-    info!(message = "All scale statistics retrieved", statistics = ?statistics_map);
+    fn pool_state(&self) -> Option<PoolState> {
+        Some(PoolState {
+            size: self.pool.size(),
+            idle: self.pool.num_idle() as u32,
+            max: self.pool.options().get_max_connections(),
+        })
+    }
 
-    Ok(statistics_map)
-}
+    async fn ping(&self) -> MindPulseResult<()> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                warn!(message = "Database ping failed", error = ?e);
+                e
+            })?;
 
-/// 插入完成的测试记录
-pub async fn insert_completed_test(
-    id: u16,
-    client_type: ClientType,
-    ip_address: &str,
-) -> MindPulseResult<u64> {
-    trace!(
-        message = "Converting client type",
-        client_type = ?client_type
-    );
-
-    trace!(message = "Getting current timestamp");
-    let timestamp = time::OffsetDateTime::now_utc().to_offset(offset!(+8));
-    debug!(message = "Current timestamp obtained", timestamp = ?timestamp);
-
-    let pool = get_database_pool().await;
-
-    trace!(
-        message = "Inserting test record",
-        scale_index = id,
-        ip_address = ip_address,
-        client_type = ?client_type
-    );
-
-    // 为了兼容旧表，id 列名暂时仍使用 scale_index
-    let result = sqlx::query(
-        "INSERT INTO statistics_ip (scale_index, ip, finished_time, client_type) VALUES ($1, $2, $3, $4)",
-    )
-    .bind(id)
-    .bind(ip_address)
-    .bind(timestamp)
-    .bind(client_type)
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        error!(
-            message = "Failed to insert test record",
-            error = ?e,
-            scale_index = id,
-            client_type = ?client_type
-        );
-        e
-    })?;
+        Ok(())
+    }
+
+    async fn backup(&self, dest: &Path) -> MindPulseResult<()> {
+        trace!(message = "Backing up database", dest = %dest.display());
+
+        // VACUUM INTO 在一个读事务中复制，得到的是一致的快照，且不会阻塞写入
+        sqlx::query("VACUUM INTO $1")
+            .bind(dest.to_string_lossy())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                error!(message = "Failed to back up database", dest = %dest.display(), error = ?e);
+                e
+            })?;
 
-    info!(
-        message = "Test record inserted successfully",
-        scale_index = id,
-        ip_address = ip_address,
-        client_type = ?client_type
-    );
+        Ok(())
+    }
 
-    Ok(result.rows_affected())
+    async fn close(&self) {
+        self.pool.close().await;
+        info!(message = "Database connection pool closed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_schema_version_matches_constant() {
+        assert_eq!(
+            SET_SCHEMA_VERSION,
+            format!("PRAGMA user_version = {}", SCHEMA_VERSION)
+        );
+    }
 }
@@ -0,0 +1,176 @@
+use std::{path::Path, sync::Arc};
+
+use salvo::async_trait;
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::config::DatabaseConfig;
+use crate::error::{MindPulseError, MindPulseResult};
+
+use super::{instrumented::InstrumentedStore, sqlite::SqliteStore};
+
+/// 可在多个任务间共享的存储后端
+pub type SharedStatisticsStore = Arc<dyn StatisticsStore>;
+
+/// 统计数据的存储后端
+#[async_trait]
+pub trait StatisticsStore: Send + Sync {
+    /// 后端名称，用于日志
+    fn backend(&self) -> &'static str;
+
+    /// 创建统计数据表
+    async fn create_statistics_table(&self) -> MindPulseResult<()>;
+
+    /// 按量表汇总完成次数：Vec<(id, count)>
+    async fn query_statistics_counts(&self) -> MindPulseResult<Vec<(u16, u64)>>;
+
+    /// 在一个事务中批量插入完成的测试记录
+    async fn insert_completed_tests(&self, records: &[CompletedTest]) -> MindPulseResult<u64>;
+
+    /// 连接池状态，没有连接池的后端返回空
+    fn pool_state(&self) -> Option<PoolState> {
+        None
+    }
+
+    /// 执行一次简单查询，确认连接可用
+    async fn ping(&self) -> MindPulseResult<()> {
+        Ok(())
+    }
+
+    /// 把数据库的一致性快照写入 `dest`，不阻塞正常读写
+    async fn backup(&self, _dest: &Path) -> MindPulseResult<()> {
+        Err(MindPulseError::Config(format!(
+            "{} 存储后端不支持在线备份",
+            self.backend()
+        )))
+    }
+
+    /// 关闭连接，等待进行中的查询结束
+    async fn close(&self) {}
+}
+
+/// 连接池状态
+#[derive(Debug, Clone, Copy)]
+pub struct PoolState {
+    /// 当前连接数
+    pub size: u32,
+    /// 空闲连接数
+    pub idle: u32,
+    /// 最大连接数
+    pub max: u32,
+}
+
+/// 客户端类型枚举
+#[derive(sqlx::Type, Default, Debug, Clone, Copy)]
+#[repr(u8)]
+pub(crate) enum ClientType {
+    Wechat = 1,
+    #[default]
+    MobileBrowser,
+}
+
+impl ClientType {
+    /// 用作指标标签的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ClientType::Wechat => "wechat",
+            ClientType::MobileBrowser => "mobile_browser",
+        }
+    }
+}
+
+impl TryFrom<u8> for ClientType {
+    type Error = MindPulseError;
+
+    fn try_from(value: u8) -> MindPulseResult<Self> {
+        match value {
+            1 => Ok(ClientType::Wechat),
+            2 => Ok(ClientType::MobileBrowser),
+            invalid => {
+                error!(message = "Invalid client type", value = invalid);
+                Err(MindPulseError::InvalidClientType(value))
+            }
+        }
+    }
+}
+
+/// 一条待写入的完成记录
+#[derive(Debug)]
+pub struct CompletedTest {
+    pub id: u16,
+    pub client_type: ClientType,
+    pub ip: String,
+    pub finished_time: OffsetDateTime,
+}
+
+impl CompletedTest {
+    /// `offset` 为完成时间使用的时区
+    pub fn new(id: u16, client_type: ClientType, ip: &str, offset: UtcOffset) -> Self {
+        Self {
+            id,
+            client_type,
+            ip: ip.to_owned(),
+            finished_time: OffsetDateTime::now_utc().to_offset(offset),
+        }
+    }
+}
+
+/// 打开数据库的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMode {
+    /// 读写，SQLite 数据库及其目录不存在时自动创建
+    ReadWrite,
+    /// 只读，供查询和备份等维护命令使用，数据库不存在时报错
+    ReadOnly,
+}
+
+/// 根据数据库配置选择并连接存储后端
+///
+/// - `url = "postgres://..."`：PostgreSQL（需启用 `postgres` 特性）
+/// - 其他情况使用 SQLite，路径取自 `url` 或 `path`
+pub async fn connect_statistics_store(
+    config: &DatabaseConfig,
+    mode: AccessMode,
+) -> MindPulseResult<SharedStatisticsStore> {
+    let max_connections = config.max_connections;
+
+    let store: SharedStatisticsStore = match config.url.as_deref() {
+        Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+            connect_postgres(url, max_connections).await?
+        }
+        Some(url) => {
+            Arc::new(SqliteStore::connect(url, max_connections, config.busy_timeout(), mode).await?)
+        }
+        None => Arc::new(
+            SqliteStore::connect(&config.path, max_connections, config.busy_timeout(), mode)
+                .await?,
+        ),
+    };
+
+    info!(
+        message = "Statistics store connected",
+        backend = store.backend()
+    );
+
+    Ok(Arc::new(InstrumentedStore::new(store)))
+}
+
+#[cfg(feature = "postgres")]
+async fn connect_postgres(
+    url: &str,
+    max_connections: u32,
+) -> MindPulseResult<SharedStatisticsStore> {
+    Ok(Arc::new(
+        super::postgres::PostgresStore::connect(url, max_connections).await?,
+    ))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn connect_postgres(
+    _url: &str,
+    _max_connections: u32,
+) -> MindPulseResult<SharedStatisticsStore> {
+    error!(message = "PostgreSQL support is not enabled");
+    Err(MindPulseError::Config(
+        "未启用 PostgreSQL 支持，请使用 `--features postgres` 重新编译".to_owned(),
+    ))
+}
@@ -0,0 +1,479 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::error::{MindPulseError, MindPulseResult};
+use crate::metrics::{metrics, WriterMetrics};
+use crate::scale::LIST;
+
+use super::{
+    cache::{StatisticsCache, RECONCILE_INTERVAL},
+    store::{CompletedTest, SharedStatisticsStore},
+};
+
+/// 队列容量，队列满时写入方需要等待（背压）
+const CHANNEL_CAPACITY: usize = 1024;
+/// 单个事务最多写入的记录数
+const BATCH_SIZE: usize = 64;
+/// 未攒满一批时的最长等待时间
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// 写入失败后第一次重试前的等待时间，之后每次翻倍
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// 重试等待时间的上限
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+/// 运行期间一批记录最多尝试写入的次数，超过后丢弃；关闭时改为一直重试到超时
+const MAX_ATTEMPTS: u32 = 8;
+
+/// 完成记录的后台批量写入器
+pub struct CompletionWriter {
+    sender: mpsc::Sender<CompletedTest>,
+    shutdown: Mutex<Option<(oneshot::Sender<Duration>, JoinHandle<()>)>>,
+    metrics: &'static WriterMetrics,
+}
+
+/// 后台写入任务持有的状态
+struct WriterTask {
+    store: SharedStatisticsStore,
+    cache: Arc<StatisticsCache>,
+    metrics: &'static WriterMetrics,
+    /// 待写入的批次，写入失败时保留到重试成功或放弃
+    batch: Vec<CompletedTest>,
+    /// 当前批次连续失败的次数
+    failures: u32,
+    /// 下一次重试的时刻，未失败时为空
+    retry_at: Option<Instant>,
+}
+
+impl CompletionWriter {
+    /// 启动后台写入任务
+    pub fn start(store: SharedStatisticsStore, cache: Arc<StatisticsCache>) -> Arc<Self> {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let metrics = &metrics().writer;
+
+        let task = WriterTask {
+            store,
+            cache,
+            metrics,
+            batch: Vec::with_capacity(BATCH_SIZE),
+            failures: 0,
+            retry_at: None,
+        };
+        let handle = tokio::spawn(task.run(receiver, shutdown_rx));
+
+        info!(
+            message = "Completion writer started",
+            capacity = CHANNEL_CAPACITY,
+            batch_size = BATCH_SIZE,
+            flush_interval = ?FLUSH_INTERVAL
+        );
+
+        Arc::new(Self {
+            sender,
+            shutdown: Mutex::new(Some((shutdown_tx, handle))),
+            metrics,
+        })
+    }
+
+    /// 将完成记录放入写入队列，队列已满时等待空位
+    pub async fn enqueue(&self, record: CompletedTest) -> MindPulseResult<()> {
+        let record = match self.sender.try_send(record) {
+            Ok(()) => None,
+            Err(mpsc::error::TrySendError::Full(record)) => {
+                self.metrics.queue_full.inc();
+                warn!(
+                    message = "Completion queue is full, waiting for writer",
+                    capacity = CHANNEL_CAPACITY
+                );
+                Some(record)
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                return Err(MindPulseError::Unavailable("服务正在关闭"))
+            }
+        };
+
+        if let Some(record) = record {
+            self.sender.send(record).await.map_err(|_| {
+                error!(message = "Completion writer closed while waiting");
+                MindPulseError::Unavailable("服务正在关闭")
+            })?;
+        }
+
+        self.metrics.enqueued.inc();
+
+        Ok(())
+    }
+
+    /// 停止接收新记录，并等待队列中剩余的记录全部写入
+    ///
+    /// 写入失败时一直重试，超过 `timeout` 后放弃剩余的记录。
+    pub async fn shutdown(&self, timeout: Duration) {
+        let shutdown = self.shutdown.lock().ok().and_then(|mut s| s.take());
+        let Some((shutdown_tx, handle)) = shutdown else {
+            return;
+        };
+
+        info!(message = "Flushing pending completion records", timeout = ?timeout);
+
+        let _ = shutdown_tx.send(timeout);
+        if let Err(e) = handle.await {
+            error!(message = "Completion writer task failed", error = ?e);
+        }
+
+        let metrics = self.metrics;
+        info!(
+            message = "Completion writer metrics",
+            enqueued = metrics.enqueued.get(),
+            queue_full = metrics.queue_full.get(),
+            flushed = metrics.flushed.get(),
+            failed = metrics.failed.get(),
+            retries = metrics.retries.get(),
+            batches = metrics.batches.get()
+        );
+    }
+
+    /// 队列中等待写入的记录数
+    pub fn queue_depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+}
+
+impl WriterTask {
+    async fn run(
+        mut self,
+        mut receiver: mpsc::Receiver<CompletedTest>,
+        mut shutdown: oneshot::Receiver<Duration>,
+    ) {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // 对账和写入在同一个任务中串行执行，查询结果不会覆盖并发写入的计数；
+        // 启动时刚加载过缓存，第一次对账推迟一个周期
+        let mut reconcile =
+            tokio::time::interval_at(Instant::now() + RECONCILE_INTERVAL, RECONCILE_INTERVAL);
+        reconcile.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        let timeout = loop {
+            let limit = BATCH_SIZE - self.batch.len();
+            tokio::select! {
+                // 批次已满且在等待重试时不再接收，队列满后由写入方等待
+                received = receiver.recv_many(&mut self.batch, limit), if limit > 0 => {
+                    if received == 0 {
+                        break Duration::ZERO;
+                    }
+                    if self.batch.len() >= BATCH_SIZE {
+                        self.flush(&receiver).await;
+                    }
+                }
+                _ = interval.tick() => {
+                    self.flush(&receiver).await;
+                }
+                _ = reconcile.tick() => {
+                    if let Err(e) = self.cache.reconcile(&self.store).await {
+                        error!(message = "Failed to reconcile statistics cache", error = ?e);
+                    }
+                }
+                timeout = &mut shutdown => {
+                    break timeout.unwrap_or_default();
+                }
+            }
+        };
+
+        // 拒绝新的记录，把已入队的记录全部写完
+        receiver.close();
+        let deadline = Instant::now() + timeout;
+        loop {
+            let limit = BATCH_SIZE - self.batch.len();
+            if limit > 0 {
+                receiver.recv_many(&mut self.batch, limit).await;
+            }
+            if self.batch.is_empty() {
+                break;
+            }
+
+            if !self.flush_until(&receiver, deadline).await {
+                // 超时后不再尝试，队列中剩余的记录一并丢弃
+                let mut remaining = Vec::new();
+                while receiver.recv_many(&mut remaining, CHANNEL_CAPACITY).await > 0 {}
+                self.batch.append(&mut remaining);
+                self.discard("shutdown timeout exceeded");
+                break;
+            }
+        }
+
+        info!(message = "Completion writer stopped");
+    }
+
+    /// 运行期间的写入，失败时保留批次并按指数退避重试，超过次数后丢弃
+    async fn flush(&mut self, receiver: &mpsc::Receiver<CompletedTest>) {
+        if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return;
+        }
+
+        if self.try_flush(receiver).await {
+            return;
+        }
+
+        if self.failures >= MAX_ATTEMPTS {
+            self.discard("retries exhausted");
+        } else {
+            self.retry_at = Some(Instant::now() + retry_delay(self.failures));
+        }
+    }
+
+    /// 关闭时的写入，失败后一直重试到 `deadline`，最后一次在 `deadline` 时尝试
+    async fn flush_until(
+        &mut self,
+        receiver: &mpsc::Receiver<CompletedTest>,
+        deadline: Instant,
+    ) -> bool {
+        loop {
+            if self.try_flush(receiver).await {
+                return true;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            tokio::time::sleep_until(Instant::min(now + retry_delay(self.failures), deadline))
+                .await;
+        }
+    }
+
+    /// 在一个事务中写入当前批次，成功时清空批次，失败时保留
+    async fn try_flush(&mut self, receiver: &mpsc::Receiver<CompletedTest>) -> bool {
+        if self.batch.is_empty() {
+            return true;
+        }
+
+        let size = self.batch.len() as u64;
+        match self.store.insert_completed_tests(&self.batch).await {
+            Ok(_) => {
+                for record in self.batch.iter() {
+                    self.cache.increment(record.id);
+
+                    let scale = LIST
+                        .iter()
+                        .find(|p| p.id == record.id)
+                        .map_or("unknown", |p| p.slug);
+                    metrics().record_completion(scale, record.client_type.as_str());
+                }
+                self.metrics.flushed.inc_by(size);
+                self.metrics.batches.inc();
+                debug!(
+                    message = "Completion batch flushed",
+                    size,
+                    queued = receiver.len(),
+                    attempts = self.failures + 1
+                );
+
+                self.batch.clear();
+                self.failures = 0;
+                self.retry_at = None;
+                true
+            }
+            Err(e) => {
+                self.failures += 1;
+                self.metrics.retries.inc();
+                warn!(
+                    message = "Failed to flush completion batch, will retry",
+                    size,
+                    attempt = self.failures,
+                    error = ?e
+                );
+                false
+            }
+        }
+    }
+
+    /// 放弃当前批次，记录丢失的数量
+    fn discard(&mut self, reason: &'static str) {
+        let lost = self.batch.len() as u64;
+        self.metrics.failed.inc_by(lost);
+        error!(
+            message = "Completion records lost",
+            lost,
+            attempts = self.failures,
+            reason
+        );
+
+        self.batch.clear();
+        self.failures = 0;
+        self.retry_at = None;
+    }
+}
+
+/// 第 `failures` 次失败后的重试等待时间
+fn retry_delay(failures: u32) -> Duration {
+    RETRY_DELAY
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use salvo::async_trait;
+    use time::UtcOffset;
+    use tokio::sync::Notify;
+
+    use super::super::{
+        memory::MemoryStore,
+        store::{ClientType, StatisticsStore},
+    };
+    use super::*;
+
+    /// 抑郁自评量表
+    const SDS: u16 = 7;
+
+    async fn start(store: &Arc<MemoryStore>) -> (Arc<StatisticsCache>, Arc<CompletionWriter>) {
+        let cache = StatisticsCache::load(store.clone()).await.unwrap();
+        let writer = CompletionWriter::start(store.clone(), cache.clone());
+        (cache, writer)
+    }
+
+    fn record() -> CompletedTest {
+        CompletedTest::new(SDS, ClientType::Wechat, "127.0.0.1", UtcOffset::UTC)
+    }
+
+    /// 所有重试等待时间之和，足够用完 `MAX_ATTEMPTS` 次尝试
+    fn all_retries() -> Duration {
+        (1..MAX_ATTEMPTS).map(retry_delay).sum::<Duration>() + FLUSH_INTERVAL * MAX_ATTEMPTS
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_batch_is_retried() {
+        let store = Arc::new(MemoryStore::failing(2));
+        let (cache, writer) = start(&store).await;
+
+        writer.enqueue(record()).await.unwrap();
+        tokio::time::sleep(FLUSH_INTERVAL).await;
+        assert_eq!(store.count(SDS), 0);
+
+        tokio::time::sleep(all_retries()).await;
+        assert_eq!(store.count(SDS), 1);
+        assert_eq!(cache.get(SDS), 1);
+
+        writer.shutdown(Duration::from_secs(1)).await;
+        assert_eq!(store.count(SDS), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn batch_is_discarded_after_max_attempts() {
+        let store = Arc::new(MemoryStore::failing(MAX_ATTEMPTS));
+        let (cache, writer) = start(&store).await;
+
+        writer.enqueue(record()).await.unwrap();
+        tokio::time::sleep(all_retries()).await;
+        assert_eq!(store.count(SDS), 0);
+        assert_eq!(cache.get(SDS), 0);
+
+        // 丢弃后不再重试旧批次，新的记录正常写入
+        writer.enqueue(record()).await.unwrap();
+        tokio::time::sleep(FLUSH_INTERVAL * 2).await;
+        assert_eq!(store.count(SDS), 1);
+        assert_eq!(cache.get(SDS), 1);
+
+        writer.shutdown(Duration::from_secs(1)).await;
+        assert_eq!(store.count(SDS), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_flushes_queued_records() {
+        let store = Arc::new(MemoryStore::failing(3));
+        let (cache, writer) = start(&store).await;
+
+        let queued = BATCH_SIZE * 2 + 1;
+        for _ in 0..queued {
+            writer.enqueue(record()).await.unwrap();
+        }
+        writer.shutdown(Duration::from_secs(60)).await;
+
+        assert_eq!(store.count(SDS), queued as u64);
+        assert_eq!(cache.get(SDS), queued as u64);
+        assert!(writer.enqueue(record()).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_gives_up_after_timeout() {
+        let store = Arc::new(MemoryStore::failing(u32::MAX));
+        let (cache, writer) = start(&store).await;
+
+        for _ in 0..3 {
+            writer.enqueue(record()).await.unwrap();
+        }
+        let timeout = Duration::from_secs(5);
+        let started = Instant::now();
+        writer.shutdown(timeout).await;
+
+        assert!(started.elapsed() >= timeout);
+        assert!(started.elapsed() < timeout + MAX_RETRY_DELAY);
+        assert_eq!(store.count(SDS), 0);
+        assert_eq!(cache.get(SDS), 0);
+    }
+
+    /// 统计查询读取计数后停在 `gate` 上，直到测试放行
+    #[derive(Default)]
+    struct GatedStore {
+        inner: MemoryStore,
+        armed: AtomicBool,
+        queried: Notify,
+        gate: Notify,
+    }
+
+    #[async_trait]
+    impl StatisticsStore for GatedStore {
+        fn backend(&self) -> &'static str {
+            "gated"
+        }
+
+        async fn create_statistics_table(&self) -> MindPulseResult<()> {
+            Ok(())
+        }
+
+        async fn query_statistics_counts(&self) -> MindPulseResult<Vec<(u16, u64)>> {
+            let rows = self.inner.query_statistics_counts().await?;
+            if self.armed.swap(false, Ordering::SeqCst) {
+                self.queried.notify_one();
+                self.gate.notified().await;
+            }
+            Ok(rows)
+        }
+
+        async fn insert_completed_tests(&self, records: &[CompletedTest]) -> MindPulseResult<u64> {
+            self.inner.insert_completed_tests(records).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconcile_does_not_overwrite_concurrent_flushes() {
+        let store = Arc::new(GatedStore::default());
+        let cache = StatisticsCache::load(store.clone()).await.unwrap();
+        let writer = CompletionWriter::start(store.clone(), cache.clone());
+
+        // 对账查询已读到旧的计数，尚未写回缓存
+        store.armed.store(true, Ordering::SeqCst);
+        tokio::time::sleep(RECONCILE_INTERVAL).await;
+        store.queried.notified().await;
+
+        for _ in 0..3 {
+            writer.enqueue(record()).await.unwrap();
+        }
+        tokio::time::sleep(FLUSH_INTERVAL * 2).await;
+
+        store.gate.notify_one();
+        writer.shutdown(Duration::from_secs(1)).await;
+
+        assert_eq!(store.inner.count(SDS), 3);
+        assert_eq!(cache.get(SDS), 3);
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram, Meter},
+    propagation::Extractor,
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+    Resource,
+};
+use salvo::http::HeaderMap;
+use tracing::{subscriber::Subscriber, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{registry::LookupSpan, EnvFilter, Layer};
+
+use crate::config::{OtelProtocol, OtelSettings};
+use crate::error::{MindPulseError, MindPulseResult};
+use crate::metrics::{metrics, DB_BUCKETS};
+use crate::statistics::Statistics;
+
+/// OTLP/HTTP 的链路导出路径
+const HTTP_TRACES_PATH: &str = "/v1/traces";
+/// OTLP/HTTP 的指标导出路径
+const HTTP_METRICS_PATH: &str = "/v1/metrics";
+
+/// 指标的作用域名称，Collector 转为 Prometheus 格式时名称与 `/metrics` 一致
+const METER_NAME: &str = "mind_pulse";
+
+static OTEL_METRICS: OnceLock<OtelMetrics> = OnceLock::new();
+
+/// 链路和指标导出器，退出前需要调用 `shutdown` 发送缓冲的数据
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// 按配置创建导出器和对应的 tracing 层，未配置地址时返回空
+    pub fn init<S>(
+        settings: &OtelSettings,
+    ) -> MindPulseResult<Option<(Self, impl Layer<S> + Send + Sync)>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+    {
+        let Some(endpoint) = settings.endpoint.as_deref() else {
+            return Ok(None);
+        };
+
+        let exporter = match settings.protocol {
+            OtelProtocol::Grpc => SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build(),
+            OtelProtocol::Http => SpanExporter::builder()
+                .with_http()
+                .with_endpoint(http_endpoint(endpoint, HTTP_TRACES_PATH))
+                .build(),
+        }
+        .map_err(|e| MindPulseError::Config(format!("无法创建 OTLP 导出器：{}", e)))?;
+
+        let metric_exporter = match settings.protocol {
+            OtelProtocol::Grpc => MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build(),
+            OtelProtocol::Http => MetricExporter::builder()
+                .with_http()
+                .with_endpoint(http_endpoint(endpoint, HTTP_METRICS_PATH))
+                .build(),
+        }
+        .map_err(|e| MindPulseError::Config(format!("无法创建 OTLP 指标导出器：{}", e)))?;
+
+        let resource = Resource::builder()
+            .with_service_name(settings.service_name.clone())
+            .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+            .build();
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            // 上游已决定是否采样时沿用其决定
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                settings.sample_ratio,
+            ))))
+            .with_resource(resource.clone())
+            .build();
+
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(
+                PeriodicReader::builder(metric_exporter)
+                    .with_interval(Duration::from_secs(settings.metric_interval_secs))
+                    .build(),
+            )
+            .with_resource(resource)
+            .build();
+        global::set_meter_provider(meter_provider.clone());
+        let _ = OTEL_METRICS.set(OtelMetrics::new(&global::meter(METER_NAME)));
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(EnvFilter::new(&settings.filter));
+
+        Ok(Some((
+            Self {
+                provider,
+                meter_provider,
+            },
+            layer,
+        )))
+    }
+
+    /// 发送缓冲的 span 和最后一次指标，然后关闭导出器
+    pub async fn shutdown(self) {
+        let Self {
+            provider,
+            meter_provider,
+        } = self;
+
+        // 关闭时会阻塞等待导出完成
+        let result =
+            tokio::task::spawn_blocking(move || (meter_provider.shutdown(), provider.shutdown()))
+                .await;
+
+        match result {
+            Ok((metrics, traces)) => {
+                if let Err(e) = metrics {
+                    error!(message = "Failed to shut down metric exporter", error = ?e);
+                }
+                match traces {
+                    Ok(()) => info!(message = "Telemetry exporter stopped"),
+                    Err(e) => {
+                        error!(message = "Failed to shut down telemetry exporter", error = ?e)
+                    }
+                }
+            }
+            Err(e) => error!(message = "Telemetry shutdown task failed", error = ?e),
+        }
+    }
+}
+
+/// 代码中设置的 OTLP/HTTP 地址不会自动追加路径
+fn http_endpoint(endpoint: &str, path: &str) -> String {
+    let base = endpoint
+        .trim_end_matches('/')
+        .trim_end_matches(HTTP_TRACES_PATH)
+        .trim_end_matches(HTTP_METRICS_PATH);
+    format!("{}{}", base, path)
+}
+
+/// 通过 OTLP 推送的指标，与 `/metrics` 中的指标同名（不含单位和 `_total` 后缀）
+///
+/// 计数器和直方图在记录 Prometheus 指标时同时记录，写入队列的计数器在导出时读取。
+pub struct OtelMetrics {
+    http_requests: Counter<u64>,
+    http_duration: Histogram<f64>,
+    completions: Counter<u64>,
+    db_duration: Histogram<f64>,
+    db_errors: Counter<u64>,
+}
+
+impl OtelMetrics {
+    fn new(meter: &Meter) -> Self {
+        let writer_counter = |name: &'static str, description: &'static str, read: fn() -> u64| {
+            meter
+                .u64_observable_counter(name)
+                .with_description(description)
+                .with_callback(move |observer| observer.observe(read(), &[]))
+                .build();
+        };
+        writer_counter("writer_enqueued", "已入队的完成记录数", || {
+            metrics().writer.enqueued.get()
+        });
+        writer_counter(
+            "writer_queue_full",
+            "入队时队列已满的次数",
+            || metrics().writer.queue_full.get(),
+        );
+        writer_counter(
+            "writer_flushed",
+            "已写入数据库的完成记录数",
+            || metrics().writer.flushed.get(),
+        );
+        writer_counter(
+            "writer_failed",
+            "写入失败而丢弃的完成记录数",
+            || metrics().writer.failed.get(),
+        );
+        writer_counter("writer_retries", "写入失败后重试的次数", || {
+            metrics().writer.retries.get()
+        });
+        writer_counter("writer_batches", "已提交的写入事务数", || {
+            metrics().writer.batches.get()
+        });
+
+        Self {
+            http_requests: meter
+                .u64_counter("http_requests")
+                .with_description("HTTP 请求数")
+                .build(),
+            http_duration: meter
+                .f64_histogram("http_request_duration")
+                .with_description("HTTP 请求耗时（秒）")
+                .with_unit("s")
+                .with_boundaries(prometheus::DEFAULT_BUCKETS.to_vec())
+                .build(),
+            completions: meter
+                .u64_counter("completions")
+                .with_description("写入数据库的完成记录数")
+                .build(),
+            db_duration: meter
+                .f64_histogram("db_query_duration")
+                .with_description("数据库操作耗时（秒）")
+                .with_unit("s")
+                .with_boundaries(DB_BUCKETS.to_vec())
+                .build(),
+            db_errors: meter
+                .u64_counter("db_errors")
+                .with_description("数据库操作失败次数")
+                .build(),
+        }
+    }
+
+    pub fn observe_request(&self, route: &str, method: &str, status: u16, duration: Duration) {
+        let attributes = [
+            KeyValue::new("route", route.to_owned()),
+            KeyValue::new("method", method.to_owned()),
+            KeyValue::new("status", i64::from(status)),
+        ];
+        self.http_requests.add(1, &attributes);
+        self.http_duration
+            .record(duration.as_secs_f64(), &attributes);
+    }
+
+    pub fn observe_db(&self, operation: &str, duration: Duration, success: bool) {
+        let attributes = [KeyValue::new("operation", operation.to_owned())];
+        self.db_duration.record(duration.as_secs_f64(), &attributes);
+        if !success {
+            self.db_errors.add(1, &attributes);
+        }
+    }
+
+    pub fn record_completion(&self, scale: &str, client_type: &str) {
+        self.completions.add(
+            1,
+            &[
+                KeyValue::new("scale", scale.to_owned()),
+                KeyValue::new("client_type", client_type.to_owned()),
+            ],
+        );
+    }
+}
+
+/// 已启用导出时返回 OTLP 指标
+pub fn otel_metrics() -> Option<&'static OtelMetrics> {
+    OTEL_METRICS.get()
+}
+
+/// 导出连接池和写入队列的状态，未启用导出时不做任何事
+pub fn observe_statistics(statistics: &Arc<Statistics>) {
+    if otel_metrics().is_none() {
+        return;
+    }
+
+    let meter = global::meter(METER_NAME);
+
+    // 只持有弱引用，关闭后不再上报
+    let weak = Arc::downgrade(statistics);
+    meter
+        .i64_observable_gauge("db_pool_connections")
+        .with_description("连接池中的连接数")
+        .with_callback(move |observer| {
+            if let Some(pool) = weak.upgrade().and_then(|s| s.pool_state()) {
+                let idle = i64::from(pool.idle);
+                observer.observe(idle, &[KeyValue::new("state", "idle")]);
+                observer.observe(
+                    i64::from(pool.size) - idle,
+                    &[KeyValue::new("state", "active")],
+                );
+            }
+        })
+        .build();
+
+    let weak = Arc::downgrade(statistics);
+    meter
+        .i64_observable_gauge("db_pool_max_connections")
+        .with_description("连接池的最大连接数")
+        .with_callback(move |observer| {
+            if let Some(pool) = weak.upgrade().and_then(|s| s.pool_state()) {
+                observer.observe(i64::from(pool.max), &[]);
+            }
+        })
+        .build();
+
+    let weak = Arc::downgrade(statistics);
+    meter
+        .i64_observable_gauge("writer_queue_depth")
+        .with_description("等待写入的完成记录数")
+        .with_callback(move |observer| {
+            if let Some(statistics) = weak.upgrade() {
+                observer.observe(statistics.queue_depth() as i64, &[]);
+            }
+        })
+        .build();
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// 如果请求带有 `traceparent`，把请求的 span 接到上游的链路中
+pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(headers)));
+    // 未启用导出时 span 没有链路上下文，忽略即可
+    let _ = span.set_parent(parent);
+}
@@ -0,0 +1,110 @@
+use std::{
+    future::ready,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use futures_util::stream::{self, Stream, StreamExt};
+use salvo::conn::rustls::{Keycert, RustlsConfig, ServerConfig};
+use salvo::conn::Acceptor;
+use salvo::prelude::*;
+
+use crate::error::{MindPulseError, MindPulseResult};
+
+/// 读取证书和私钥，并确认两者可以组成有效的 TLS 配置
+fn load(cert: &Path, key: &Path) -> MindPulseResult<RustlsConfig> {
+    let keycert = Keycert::new()
+        .cert_from_path(cert)
+        .map_err(|e| {
+            MindPulseError::Config(format!("无法读取 TLS 证书 {}：{}", cert.display(), e))
+        })?
+        .key_from_path(key)
+        .map_err(|e| {
+            MindPulseError::Config(format!("无法读取 TLS 私钥 {}：{}", key.display(), e))
+        })?;
+
+    let config = RustlsConfig::new(keycert);
+
+    // 提前构建一次，证书与私钥不匹配时在这里报错，而不是等到第一次握手
+    TryInto::<ServerConfig>::try_into(config.clone()).map_err(|e| {
+        MindPulseError::Config(format!(
+            "TLS 证书 {} 或私钥 {} 无效：{}",
+            cert.display(),
+            key.display(),
+            e
+        ))
+    })?;
+
+    Ok(config)
+}
+
+/// 首项为启动时读取的配置，之后每次收到 SIGHUP 重新读取
+///
+/// 重新读取失败时不产生新配置，监听器继续使用原来的证书。
+pub fn config_stream(
+    cert: &Path,
+    key: &Path,
+) -> MindPulseResult<impl Stream<Item = RustlsConfig> + Send + 'static> {
+    let initial = load(cert, key)?;
+    info!(
+        message = "TLS certificate loaded",
+        cert = %cert.display(),
+        key = %key.display()
+    );
+
+    #[cfg(unix)]
+    let reloads = {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let hangup = signal(SignalKind::hangup()).map_err(|e| {
+            error!(message = "Failed to listen for SIGHUP", error = ?e);
+            e
+        })?;
+
+        stream::unfold(
+            (hangup, cert.to_path_buf(), key.to_path_buf()),
+            |(mut hangup, cert, key): (_, PathBuf, PathBuf)| async move {
+                hangup.recv().await?;
+
+                let config = match load(&cert, &key) {
+                    Ok(config) => {
+                        info!(
+                            message = "TLS certificate reloaded",
+                            cert = %cert.display(),
+                            key = %key.display()
+                        );
+                        Some(config)
+                    }
+                    Err(e) => {
+                        error!(
+                            message = "Failed to reload TLS certificate, keeping the current one",
+                            error = %e
+                        );
+                        None
+                    }
+                };
+
+                Some((config, (hangup, cert, key)))
+            },
+        )
+        .filter_map(ready)
+    };
+
+    #[cfg(not(unix))]
+    let reloads = stream::empty();
+
+    Ok(stream::once(ready(initial)).chain(reloads))
+}
+
+/// 在 `address` 上监听 HTTP，把所有请求永久重定向到 `https_port`
+pub async fn redirect_acceptor(address: SocketAddr) -> MindPulseResult<impl Acceptor> {
+    TcpListener::new(address).try_bind().await.map_err(|e| {
+        error!(message = "Failed to bind HTTPS redirect listener", address = %address, error = ?e);
+        MindPulseError::Config(format!("无法监听重定向地址 {}：{}", address, e))
+    })
+}
+
+/// 只做 HTTPS 重定向的服务
+pub fn redirect_service(https_port: u16) -> Service {
+    Service::new(Router::new()).hoop(ForceHttps::new().https_port(https_port))
+}